@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use nebula_manager_lib::vault::{search, Entry};
+use std::hint::black_box;
+
+const WORDS: [&str; 16] = [
+    "mail", "bank", "cloud", "shop", "forum", "news", "travel", "games",
+    "music", "video", "photo", "work", "school", "health", "energy", "phone",
+];
+
+/// A made-up but varied login, `i` picks its words, hosts and usage.
+fn entry(i: usize) -> Entry {
+    let word = |n: usize| WORDS[n % WORDS.len()];
+    let last_used = i.is_multiple_of(3).then(|| format!("2024-{:02}-{:02}T12:00:00Z", 1 + i % 12, 1 + i % 28));
+
+    serde_json::from_value(serde_json::json!({
+        "id": uuid::Uuid::new_v4(),
+        "createdAt": "2023-01-01T00:00:00Z",
+        "modifiedAt": "2023-01-01T00:00:00Z",
+        "lastUsed": last_used,
+        "uses": i % 50,
+        "label": format!("{} {} {}", word(i), word(i / 16), i),
+        "url": format!("https://{}{}.example.com/login", word(i / 7), i % 100),
+        "name": format!("user{}@{}.org", i, word(i / 3)),
+        "password": "",
+        "passwordStrength": "Weak",
+        "favorite": i.is_multiple_of(97),
+        "tags": [word(i / 5), word(i / 11)],
+    }))
+    .unwrap()
+}
+
+fn search_10k(c: &mut Criterion) {
+    let entries: Vec<Entry> = (0..10_000).map(entry).collect();
+    let mut group = c.benchmark_group("search_10k");
+
+    for query in ["m", "mail", "bank 12", "clod", "user42", "exmpl", "shop travel", "zzz"] {
+        group.bench_with_input(BenchmarkId::from_parameter(query), query, |b, query| {
+            b.iter(|| search::search(entries.iter(), black_box(query), 50))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, search_10k);
+criterion_main!(benches);
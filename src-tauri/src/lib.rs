@@ -1,6 +1,6 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
-use std::{sync::{Arc, Mutex}, time::Duration};
+use std::{sync::{Arc, Mutex}, time::{Duration, SystemTime}};
 use regex::Regex;
 use tauri::{Emitter, Manager, RunEvent, State, WindowEvent};
 
@@ -10,7 +10,21 @@ use uuid::Uuid;
 pub use vault::Vault;
 
 use crate::vault::{
-    entry::{EntryPublic, UpdateEntry},
+    audit::{self, BreachResult, SecurityReport},
+    autolock::AutoLockSettings,
+    backup::{BackupDetails, BackupInfo, BackupPolicy},
+    throttle::ThrottleStatus,
+    totp::{TotpCode, TotpConfig},
+    trash::TrashSettings,
+    crypto::{KdfParams, VaultCryptoManager},
+    entry::{CustomFieldKind, Entry, EntryPublic, PasswordHistoryInfo, UpdateEntry, UrlMatchMode},
+    folder::{EntryFilter, Folder},
+    export::ExportFormat,
+    generator::{self, GeneratorOptions, GeneratorPreset},
+    import::{self, ImportFormat, ImportSummary},
+    item::{Item, ItemPublic, UpdateItem, WifiSecurity},
+    note::{SecureNote, SecureNotePublic, UpdateSecureNote},
+    search::SearchResult,
     vault::{EntryUseResult, VaultChangeEvent, VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult, VaultStatus},
 };
 
@@ -53,6 +67,9 @@ where
         })
     }
 
+    // Every command counts as activity for the auto-lock timer
+    v.touch();
+
     f(&mut v)
 }
 
@@ -109,6 +126,278 @@ fn validate<T: AsRef<str>>(fields: Vec<ValidationField<T>>) -> VaultResult<()> {
     Ok(())
 }
 
+fn validate_custom_field(name: &String, kind: &CustomFieldKind, value: Option<&String>) -> VaultResult<()> {
+    validate(vec![ValidationField {
+        value: name,
+        rules: vec![ValidationRule::Required],
+        name: "Custom field name",
+    }])?;
+
+    let pattern = match kind {
+        CustomFieldKind::Url => r"^[a-zA-Z][a-zA-Z0-9+.-]*://\S+$",
+        CustomFieldKind::Email => r"^[^@\s]+@[^@\s]+\.[^@\s]+$",
+        CustomFieldKind::Number => r"^-?\d+(\.\d+)?$",
+        CustomFieldKind::Date => r"^\d{4}-\d{2}-\d{2}$",
+        CustomFieldKind::Text | CustomFieldKind::Hidden => return Ok(()),
+    };
+
+    // Empty values are allowed, and a missing one keeps the stored value
+    match value {
+        Some(value) if !value.is_empty() => validate(vec![ValidationField {
+            value,
+            rules: vec![ValidationRule::RegEx(pattern.into())],
+            name: "Custom field value",
+        }]),
+        _ => Ok(()),
+    }
+}
+
+fn validate_totp(secret: &str) -> VaultResult<()> {
+    if secret.trim().is_empty() {
+        return Ok(());
+    }
+
+    TotpConfig::parse(secret).map(|_| ()).map_err(|e| VaultError {
+        kind: VaultErrorKind::Validation,
+        severity: VaultErrorSeverity::Blocking,
+        message: format!("TOTP secret is invalid: {}", e),
+        code: "E_VALIDATION_TOTP",
+    })
+}
+
+// Other match modes take whatever the user typed, unparseable URLs just never match
+fn validate_url_patterns<'a>(mode: UrlMatchMode, urls: impl Iterator<Item = &'a String>) -> VaultResult<()> {
+    if mode != UrlMatchMode::RegEx {
+        return Ok(());
+    }
+
+    for url in urls.filter(|u| !u.trim().is_empty()) {
+        Regex::new(url.trim()).map_err(|_| VaultError {
+            kind: VaultErrorKind::Validation,
+            severity: VaultErrorSeverity::Blocking,
+            message: format!("URL pattern '{}' is not a valid regular expression", url),
+            code: "E_VALIDATION_URL_PATTERN",
+        })?;
+    }
+
+    Ok(())
+}
+
+fn validate_entry(entry: &Entry) -> VaultResult<()> {
+    let fields = vec![
+        ValidationField {
+            value: &entry.label,
+            rules: vec![ValidationRule::Required],
+            name: "Label",
+        },
+        ValidationField {
+            value: &entry.name,
+            rules: vec![ValidationRule::Required],
+            name: "Username",
+        },
+        ValidationField {
+            value: &entry.password,
+            rules: vec![ValidationRule::Required],
+            name: "Password",
+        },
+    ];
+
+    validate(fields)?;
+
+    for field in &entry.fields {
+        validate_custom_field(&field.name, &field.kind, Some(&field.value))?;
+    }
+
+    if let Some(totp) = &entry.totp {
+        validate_totp(totp)?;
+    }
+
+    validate_url_patterns(entry.url_match, entry.all_urls())
+}
+
+// Optional values only have to match when they are filled in
+const OPTIONAL_EMAIL: &str = r"^([^@\s]+@[^@\s]+\.[^@\s]+)?$";
+const OPTIONAL_DATE: &str = r"^(\d{4}-\d{2}-\d{2})?$";
+
+fn validate_item(item: &Item) -> VaultResult<()> {
+    let fields = match item {
+        Item::Login(entry) => return validate_entry(entry),
+        Item::Card(card) => vec![
+            ValidationField {
+                value: &card.meta.label,
+                rules: vec![ValidationRule::Required],
+                name: "Label",
+            },
+            ValidationField {
+                value: &card.number,
+                rules: vec![ValidationRule::Required, ValidationRule::RegEx(r"^[0-9 -]{12,23}$".into())],
+                name: "Card number",
+            },
+            ValidationField {
+                value: &card.exp_month,
+                rules: vec![ValidationRule::RegEx(r"^(0[1-9]|1[0-2])?$".into())],
+                name: "Expiration month",
+            },
+            ValidationField {
+                value: &card.exp_year,
+                rules: vec![ValidationRule::RegEx(r"^(\d{4})?$".into())],
+                name: "Expiration year",
+            },
+            ValidationField {
+                value: &card.cvv,
+                rules: vec![ValidationRule::RegEx(r"^(\d{3,4})?$".into())],
+                name: "Security code",
+            },
+            ValidationField {
+                value: &card.pin,
+                rules: vec![ValidationRule::RegEx(r"^(\d{4,12})?$".into())],
+                name: "PIN",
+            },
+        ],
+        Item::Identity(identity) => vec![
+            ValidationField {
+                value: &identity.meta.label,
+                rules: vec![ValidationRule::Required],
+                name: "Label",
+            },
+            ValidationField {
+                value: &identity.email,
+                rules: vec![ValidationRule::RegEx(OPTIONAL_EMAIL.into())],
+                name: "Email",
+            },
+        ],
+        Item::Wifi(wifi) => {
+            // Open networks are the only ones without a password
+            let password_rules = match wifi.security {
+                WifiSecurity::None => vec![],
+                _ => vec![ValidationRule::Required],
+            };
+
+            vec![
+                ValidationField {
+                    value: &wifi.meta.label,
+                    rules: vec![ValidationRule::Required],
+                    name: "Label",
+                },
+                ValidationField {
+                    value: &wifi.ssid,
+                    rules: vec![ValidationRule::Required],
+                    name: "Network name",
+                },
+                ValidationField {
+                    value: &wifi.password,
+                    rules: password_rules,
+                    name: "Password",
+                },
+            ]
+        }
+        Item::License(license) => vec![
+            ValidationField {
+                value: &license.meta.label,
+                rules: vec![ValidationRule::Required],
+                name: "Label",
+            },
+            ValidationField {
+                value: &license.product,
+                rules: vec![ValidationRule::Required],
+                name: "Product",
+            },
+            ValidationField {
+                value: &license.license_key,
+                rules: vec![ValidationRule::Required],
+                name: "License key",
+            },
+            ValidationField {
+                value: &license.email,
+                rules: vec![ValidationRule::RegEx(OPTIONAL_EMAIL.into())],
+                name: "Email",
+            },
+            ValidationField {
+                value: &license.purchase_date,
+                rules: vec![ValidationRule::RegEx(OPTIONAL_DATE.into())],
+                name: "Purchase date",
+            },
+            ValidationField {
+                value: &license.expires_at,
+                rules: vec![ValidationRule::RegEx(OPTIONAL_DATE.into())],
+                name: "Expiration date",
+            },
+        ],
+        Item::SshKey(key) => vec![
+            ValidationField {
+                value: &key.meta.label,
+                rules: vec![ValidationRule::Required],
+                name: "Label",
+            },
+            ValidationField {
+                value: &key.private_key,
+                rules: vec![
+                    ValidationRule::Required,
+                    ValidationRule::RegEx(r"^\s*-----BEGIN [A-Z0-9 ]*PRIVATE KEY-----".into()),
+                ],
+                name: "Private key",
+            },
+            ValidationField {
+                value: &key.public_key,
+                rules: vec![ValidationRule::RegEx(r"^(\S+ [A-Za-z0-9+/=]+( .*)?)?$".into())],
+                name: "Public key",
+            },
+        ],
+    };
+
+    validate(fields)
+}
+
+fn validate_folder_name(name: &String) -> VaultResult<()> {
+    validate(vec![ValidationField {
+        value: name,
+        rules: vec![ValidationRule::Required],
+        name: "Folder name",
+    }])
+}
+
+// Notify other windows of changes to several entries
+fn emit_entry_updates(app_handle: &tauri::AppHandle, webview_window: &tauri::WebviewWindow, entries: &[EntryPublic]) {
+    for entry in entries {
+        let _ = app_handle.emit("vault_changed", VaultChangeEvent::Update { 
+            source: webview_window.label().into(), 
+            id: entry.id,
+            new: entry.clone()
+        });
+    }
+}
+
+// The whole tree is sent, it is small and keeps every window consistent
+fn emit_folders(app_handle: &tauri::AppHandle, webview_window: &tauri::WebviewWindow, folders: Vec<Folder>) {
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Folders { 
+        source: webview_window.label().into(), 
+        folders
+    });
+}
+
+// Copies text to the clipboard, remembering its hash for vault_clear_clipboard_safe
+fn copy_secret(app_handle: &tauri::AppHandle, last_hash_state: &State<Arc<Mutex<Option<String>>>>, text: String, code: &'static str) -> VaultResult<()> {
+    let hash = sha256_hash(&text);
+
+    {
+        let mut h = last_hash_state.lock().map_err(|_| VaultError {
+            kind: VaultErrorKind::Access, 
+            severity: VaultErrorSeverity::Blocking, 
+            message: "Clipboard hash not accessible".into(),
+            code: "E_VAULT_COPY_PASS_HASH"
+        })?;
+
+        *h = Some(hash);
+    }
+
+    app_handle.clipboard().write_text(text).map_err(|_| VaultError {
+        kind: VaultErrorKind::Internal, 
+        severity: VaultErrorSeverity::Blocking, 
+        message: "Couldn't copy to clipboard".into(),
+        code,
+    })
+}
+
 // Toggle search overlay
 #[tauri::command]
 fn toggle_overlay(app: tauri::AppHandle, overlay_unlocked: State<Arc<Mutex<bool>>>) -> VaultResult<()> {
@@ -125,131 +414,822 @@ fn toggle_overlay(app: tauri::AppHandle, overlay_unlocked: State<Arc<Mutex<bool>
         return Ok(());
     }
 
-    if let Some(window) = app.get_webview_window("overlay") {
-        let is_visible = window.is_visible().unwrap_or(false);
+    if let Some(window) = app.get_webview_window("overlay") {
+        let is_visible = window.is_visible().unwrap_or(false);
+
+        if is_visible {
+            let _ = window.emit("overlay_before_hide", ());
+
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+
+                window.hide().unwrap();
+            });
+        } else {
+            window.show().unwrap();
+            let _ = window.emit("overlay_show", ());
+            window.set_focus().unwrap();
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn unlock_overlay(app_handle: tauri::AppHandle, overlay_unlocked: State<Arc<Mutex<bool>>>) -> VaultResult<()> {
+    let mut unlocked = overlay_unlocked
+        .lock()
+        .map_err(|_| VaultError {
+            kind: VaultErrorKind::Access, 
+            severity: VaultErrorSeverity::Blocking, 
+            message: "Overlay lock is not accessible".into(),
+            code: "E_TOGGLE_OVERLAY"
+        })?;
+
+    *unlocked = true;
+
+    let _ = app_handle.emit_to("overlay", "vault_ready", ());
+
+    Ok(())
+}
+
+// Auto-lock
+const AUTO_LOCK_TICK: Duration = Duration::from_secs(5);
+const SLEEP_GAP: Duration = Duration::from_secs(30);
+
+// Locks the vault and the overlay, then notifies every window
+fn lock_vault(app_handle: &tauri::AppHandle) -> VaultResult<()> {
+    let vault = app_handle.state::<Arc<Mutex<Vault>>>();
+    let overlay_unlocked = app_handle.state::<Arc<Mutex<bool>>>();
+
+    // The vault is locked even if saving fails, so the rest still has to happen
+    let saved = with_vault(&vault, "E_VAULT_LOCK", Some(WithVaultOptions::RequireUnlocked), |v| v.save_and_lock());
+
+    {
+        let mut unlocked = overlay_unlocked
+            .lock()
+            .map_err(|_| VaultError {
+                kind: VaultErrorKind::Access, 
+                severity: VaultErrorSeverity::Blocking, 
+                message: "Overlay lock is not accessible".into(),
+                code: "E_VAULT_LOCK"
+            })?;
+
+        *unlocked = false;
+    }
+
+    if let Some(overlay) = app_handle.get_webview_window("overlay") {
+        let _ = overlay.hide();
+    }
+
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Status);
+
+    saved
+}
+
+fn spawn_auto_lock_watcher(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_tick = SystemTime::now();
+
+        loop {
+            tokio::time::sleep(AUTO_LOCK_TICK).await;
+
+            // Timers stall while the machine sleeps, so a big jump in wall-clock time means we just woke up
+            let now = SystemTime::now();
+            let woke_up = now
+                .duration_since(last_tick)
+                .map(|gap| gap > AUTO_LOCK_TICK + SLEEP_GAP)
+                .unwrap_or(false);
+            last_tick = now;
+
+            let should_lock = {
+                let vault = app_handle.state::<Arc<Mutex<Vault>>>();
+                let Ok(v) = vault.lock() else { continue };
+
+                v.is_unlocked() && (v.is_idle() || (woke_up && v.get_auto_lock_settings().lock_on_sleep))
+            };
+
+            if should_lock {
+                match lock_vault(&app_handle) {
+                    Ok(()) => println!("Vault auto-locked."),
+                    Err(e) => println!("Error while auto-locking: {}", e),
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+fn vault_lock(app_handle: tauri::AppHandle) -> VaultResult<()> {
+    lock_vault(&app_handle)
+}
+
+#[tauri::command]
+fn vault_get_auto_lock_settings(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<AutoLockSettings> {
+    with_vault(&vault, "E_VAULT_GET_AUTOLOCK", None, |v| Ok(v.get_auto_lock_settings()))
+}
+
+#[tauri::command]
+fn vault_set_auto_lock_settings(vault: State<Arc<Mutex<Vault>>>, settings: AutoLockSettings) -> VaultResult<()> {
+    with_vault(&vault, "E_VAULT_SET_AUTOLOCK", Some(WithVaultOptions::RequireUnlocked), |v| v.set_auto_lock_settings(settings))
+}
+
+// Vault commands
+#[tauri::command]
+fn vault_setup(vault: State<Arc<Mutex<Vault>>>, master_password: String) -> VaultResult<()> {
+    let setup_result = with_vault(&vault, "E_VAULT_SETUP", None, |v| v.set_master_pw(&master_password))?;
+    Ok(setup_result)
+}
+
+#[tauri::command]
+fn vault_unlock(app_handle: tauri::AppHandle, vault: State<Arc<Mutex<Vault>>>, overlay_unlocked: State<Arc<Mutex<bool>>>, password: String) -> VaultResult<()> {
+    let unlock_result = with_vault(&vault, "E_VAULT_UNLOCK", None, |v| v.unlock(&password))?;
+    let _ = app_handle.emit_to("main", "vault_changed", VaultChangeEvent::Status);
+    unlock_overlay(app_handle, overlay_unlocked)?;
+    Ok(unlock_result)
+}
+
+#[tauri::command]
+fn vault_change_master_password(
+    app_handle: tauri::AppHandle,
+    vault: State<Arc<Mutex<Vault>>>,
+    old_password: String,
+    new_password: String
+) -> VaultResult<()> {
+    let fields = vec![
+        ValidationField {
+            value: &new_password,
+            rules: vec![ValidationRule::Required],
+            name: "New password",
+        },
+    ];
+
+    validate(fields)?;
+
+    with_vault(&vault, "E_VAULT_CHANGE_PW", Some(WithVaultOptions::RequireUnlocked), |v| {
+        v.change_master_password(&old_password, &new_password)
+    })?;
+
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Status);
+
+    Ok(())
+}
+
+#[tauri::command]
+fn vault_rotate_dek(app_handle: tauri::AppHandle, vault: State<Arc<Mutex<Vault>>>, password: String) -> VaultResult<()> {
+    with_vault(&vault, "E_VAULT_ROTATE_DEK", Some(WithVaultOptions::RequireUnlocked), |v| v.rotate_dek(&password))?;
+
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Status);
+
+    Ok(())
+}
+
+#[tauri::command]
+fn vault_get_kdf_params(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<KdfParams> {
+    with_vault(&vault, "E_VAULT_GET_KDF", None, |v| Ok(v.get_kdf_params()))
+}
+
+#[tauri::command]
+async fn vault_calibrate_kdf(vault: State<'_, Arc<Mutex<Vault>>>, password: String, target_ms: u64) -> VaultResult<KdfParams> {
+    if !(100..=10_000).contains(&target_ms) {
+        return Err(VaultError {
+            kind: VaultErrorKind::Validation,
+            severity: VaultErrorSeverity::Soft,
+            message: "Target unlock time must be between 100 and 10000 ms".into(),
+            code: "E_VAULT_CALIBRATE_KDF",
+        });
+    }
+
+    // Benchmark off the main thread and without holding the vault lock
+    let params = tauri::async_runtime::spawn_blocking(move || VaultCryptoManager::calibrate_kdf(Duration::from_millis(target_ms)))
+        .await
+        .map_err(|e| VaultError {
+            kind: VaultErrorKind::Internal,
+            severity: VaultErrorSeverity::Blocking,
+            message: format!("Key derivation calibration failed: {e}"),
+            code: "E_VAULT_CALIBRATE_KDF",
+        })?
+        .map_err(|e| VaultError {
+            kind: VaultErrorKind::Crypto,
+            severity: VaultErrorSeverity::Blocking,
+            message: format!("Failed to calibrate key derivation: {e}"),
+            code: "E_VAULT_CALIBRATE_KDF",
+        })?;
+
+    with_vault(&vault, "E_VAULT_CALIBRATE_KDF", Some(WithVaultOptions::RequireUnlocked), |v| {
+        v.set_kdf_params(&password, params.clone())
+    })?;
+
+    Ok(params)
+}
+
+#[tauri::command]
+fn vault_list_backups(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<Vec<BackupInfo>> {
+    with_vault(&vault, "E_VAULT_LIST_BACKUPS", None, |v| v.list_backups())
+}
+
+#[tauri::command]
+fn vault_inspect_backup(vault: State<Arc<Mutex<Vault>>>, id: String, password: String) -> VaultResult<BackupDetails> {
+    with_vault(&vault, "E_VAULT_INSPECT_BACKUP", Some(WithVaultOptions::RequireUnlocked), |v| v.inspect_backup(&id, &password))
+}
+
+#[tauri::command]
+fn vault_restore_backup(
+    app_handle: tauri::AppHandle,
+    vault: State<Arc<Mutex<Vault>>>,
+    overlay_unlocked: State<Arc<Mutex<bool>>>,
+    id: String,
+    master_password: String,
+    password: String
+) -> VaultResult<()> {
+    with_vault(&vault, "E_VAULT_RESTORE_BACKUP", Some(WithVaultOptions::RequireUnlocked), |v| {
+        v.restore_backup(&id, &master_password, &password)
+    })?;
+
+    // Every window has to reload its entries
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Status);
+    unlock_overlay(app_handle, overlay_unlocked)
+}
+
+#[tauri::command]
+fn vault_get_backup_policy(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<BackupPolicy> {
+    with_vault(&vault, "E_VAULT_GET_BACKUP_POLICY", None, |v| Ok(v.get_backup_policy()))
+}
+
+#[tauri::command]
+fn vault_set_backup_policy(vault: State<Arc<Mutex<Vault>>>, policy: BackupPolicy) -> VaultResult<()> {
+    with_vault(&vault, "E_VAULT_SET_BACKUP_POLICY", Some(WithVaultOptions::RequireUnlocked), |v| v.set_backup_policy(policy))
+}
+
+#[tauri::command]
+fn vault_get_unlock_throttle(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<ThrottleStatus> {
+    with_vault(&vault, "E_VAULT_GET_THROTTLE", None, |v| Ok(v.get_throttle_status()))
+}
+
+#[tauri::command]
+fn vault_create_entry(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow, 
+    vault: State<Arc<Mutex<Vault>>>, 
+    entry: vault::Entry
+) -> VaultResult<()> {
+    validate_entry(&entry)?;
+
+    let created = with_vault(&vault, "E_VAULT_ENTRY_CREATE", Some(WithVaultOptions::RequireUnlocked), |v| {
+        let created = v.new_entry(&entry);
+        v.save()?;
+        Ok(created)
+    })?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Create { 
+        source: webview_window.label().into(), 
+        entry: created 
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+fn vault_get_status(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<VaultStatus> {
+    with_vault(&vault, "E_VAULT_GET_STATUS", None, |v| Ok(v.get_status()))
+}
+
+#[tauri::command]
+fn vault_get_entries(vault: State<Arc<Mutex<Vault>>>, filter: Option<EntryFilter>) -> VaultResult<Vec<EntryPublic>> {
+    with_vault(&vault, "E_VAULT_GET_ENTRIES", Some(WithVaultOptions::RequireUnlocked), |v| {
+        Ok(match filter {
+            Some(filter) => v.get_entries_filtered(&filter),
+            None => v.get_entries(),
+        })
+    })
+}
+
+#[tauri::command]
+fn vault_search(vault: State<Arc<Mutex<Vault>>>, query: String, limit: Option<usize>) -> VaultResult<Vec<SearchResult>> {
+    with_vault(&vault, "E_VAULT_SEARCH", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.search(&query, limit.unwrap_or(50))))
+}
+
+#[tauri::command]
+fn vault_find_entries_for_url(vault: State<Arc<Mutex<Vault>>>, url: String) -> VaultResult<Vec<EntryPublic>> {
+    with_vault(&vault, "E_VAULT_FIND_FOR_URL", Some(WithVaultOptions::RequireUnlocked), |v| v.find_entries_for_url(&url))
+}
+
+#[tauri::command]
+fn vault_get_tags(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<Vec<String>> {
+    with_vault(&vault, "E_VAULT_GET_TAGS", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.get_tags()))
+}
+
+#[tauri::command]
+fn vault_assign_tags(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    ids: Vec<Uuid>,
+    add: Vec<String>,
+    remove: Vec<String>
+) -> VaultResult<Vec<EntryPublic>> {
+    let result = with_vault(&vault, "E_VAULT_ASSIGN_TAGS", Some(WithVaultOptions::RequireUnlocked), |v| v.assign_tags(&ids, &add, &remove))?;
+
+    emit_entry_updates(&app_handle, &webview_window, &result);
+
+    Ok(result)
+}
+
+#[tauri::command]
+fn vault_move_entries(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    ids: Vec<Uuid>,
+    folder_id: Option<Uuid>
+) -> VaultResult<Vec<EntryPublic>> {
+    let result = with_vault(&vault, "E_VAULT_MOVE_ENTRIES", Some(WithVaultOptions::RequireUnlocked), |v| v.move_entries(&ids, folder_id))?;
+
+    emit_entry_updates(&app_handle, &webview_window, &result);
+
+    Ok(result)
+}
+
+#[tauri::command]
+fn vault_get_folders(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<Vec<Folder>> {
+    with_vault(&vault, "E_VAULT_GET_FOLDERS", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.get_folders()))
+}
+
+#[tauri::command]
+fn vault_create_folder(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    name: String,
+    parent_id: Option<Uuid>
+) -> VaultResult<Folder> {
+    validate_folder_name(&name)?;
+
+    let (folder, folders) = with_vault(&vault, "E_VAULT_FOLDER_CREATE", Some(WithVaultOptions::RequireUnlocked), |v| {
+        Ok((v.create_folder(&name, parent_id)?, v.get_folders()))
+    })?;
+
+    emit_folders(&app_handle, &webview_window, folders);
+
+    Ok(folder)
+}
+
+#[tauri::command]
+fn vault_rename_folder(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    id: Uuid,
+    name: String
+) -> VaultResult<Folder> {
+    validate_folder_name(&name)?;
+
+    let (folder, folders) = with_vault(&vault, "E_VAULT_FOLDER_RENAME", Some(WithVaultOptions::RequireUnlocked), |v| {
+        Ok((v.rename_folder(&id, &name)?, v.get_folders()))
+    })?;
+
+    emit_folders(&app_handle, &webview_window, folders);
+
+    Ok(folder)
+}
+
+#[tauri::command]
+fn vault_move_folder(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    id: Uuid,
+    parent_id: Option<Uuid>
+) -> VaultResult<Folder> {
+    let (folder, folders) = with_vault(&vault, "E_VAULT_FOLDER_MOVE", Some(WithVaultOptions::RequireUnlocked), |v| {
+        Ok((v.move_folder(&id, parent_id)?, v.get_folders()))
+    })?;
+
+    emit_folders(&app_handle, &webview_window, folders);
+
+    Ok(folder)
+}
+
+#[tauri::command]
+fn vault_delete_folder(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    id: Uuid
+) -> VaultResult<()> {
+    let (moved, folders) = with_vault(&vault, "E_VAULT_FOLDER_DELETE", Some(WithVaultOptions::RequireUnlocked), |v| {
+        Ok((v.delete_folder(&id)?, v.get_folders()))
+    })?;
+
+    emit_folders(&app_handle, &webview_window, folders);
+    emit_entry_updates(&app_handle, &webview_window, &moved);
+
+    Ok(())
+}
+
+#[tauri::command]
+fn vault_get_entry_password(vault: State<Arc<Mutex<Vault>>>, id: Uuid) -> VaultResult<String> {
+    with_vault(&vault, "E_VAULT_GET_PASS", Some(WithVaultOptions::RequireUnlocked), |v| v.get_entry_password(&id))
+}
+
+#[tauri::command]
+fn vault_get_entry_notes(vault: State<Arc<Mutex<Vault>>>, id: Uuid) -> VaultResult<String> {
+    with_vault(&vault, "E_VAULT_GET_NOTES", Some(WithVaultOptions::RequireUnlocked), |v| v.get_entry_notes(&id))
+}
+
+#[tauri::command]
+fn vault_get_password_history(vault: State<Arc<Mutex<Vault>>>, id: Uuid) -> VaultResult<Vec<PasswordHistoryInfo>> {
+    with_vault(&vault, "E_VAULT_GET_HISTORY", Some(WithVaultOptions::RequireUnlocked), |v| v.get_password_history(&id))
+}
+
+#[tauri::command]
+fn vault_check_password_reuse(vault: State<Arc<Mutex<Vault>>>, password: String, exclude: Option<Uuid>) -> VaultResult<Vec<Uuid>> {
+    with_vault(&vault, "E_VAULT_CHECK_REUSE", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.find_password_reuse(&password, exclude.as_ref())))
+}
+
+#[tauri::command]
+fn vault_security_report(vault: State<Arc<Mutex<Vault>>>, max_age_days: Option<u32>) -> VaultResult<SecurityReport> {
+    with_vault(&vault, "E_VAULT_SECURITY_REPORT", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.security_report(max_age_days.unwrap_or(365))))
+}
+
+/// Checks every password against a local pwned passwords list. Only the hashes
+/// are taken from the vault, the search itself runs on a blocking thread and
+/// reports through `audit_progress` events.
+#[tauri::command]
+async fn vault_audit_breaches(
+    app_handle: tauri::AppHandle,
+    vault: State<'_, Arc<Mutex<Vault>>>,
+    path: String,
+) -> VaultResult<Vec<BreachResult>> {
+    let hashes = with_vault(&vault, "E_VAULT_AUDIT_BREACHES", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.get_password_hashes()))?;
+
+    // Roughly every percent is enough for a progress bar
+    let step = (hashes.len() / 100).max(1);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        audit::check_breaches(std::path::Path::new(&path), &hashes, |progress| {
+            if progress.checked % step == 0 || progress.checked == progress.total {
+                let _ = app_handle.emit("audit_progress", progress);
+            }
+        })
+    })
+    .await
+    .map_err(|e| VaultError {
+        kind: VaultErrorKind::Internal,
+        severity: VaultErrorSeverity::Soft,
+        message: format!("Breach audit failed: {}", e),
+        code: "E_VAULT_AUDIT_BREACHES",
+    })?
+}
+
+#[tauri::command]
+fn vault_restore_password(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow, 
+    vault: State<Arc<Mutex<Vault>>>,
+    id: Uuid,
+    history_id: Uuid,
+) -> VaultResult<EntryPublic> {
+    let result = with_vault(&vault, "E_VAULT_RESTORE_PASS", Some(WithVaultOptions::RequireUnlocked), |v| v.restore_password(&id, &history_id))?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Update { 
+        source: webview_window.label().into(), 
+        id,
+        new: result.clone()
+    });
+
+    Ok(result)
+}
+
+#[tauri::command]
+fn vault_purge_password_history(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow, 
+    vault: State<Arc<Mutex<Vault>>>,
+    id: Uuid,
+) -> VaultResult<EntryPublic> {
+    // Saved right away, so the old passwords don't linger in the vault file
+    let result = with_vault(&vault, "E_VAULT_PURGE_HISTORY", Some(WithVaultOptions::RequireUnlocked), |v| {
+        let result = v.purge_password_history(&id)?;
+        v.save()?;
+        Ok(result)
+    })?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Update { 
+        source: webview_window.label().into(), 
+        id,
+        new: result.clone()
+    });
+
+    Ok(result)
+}
+
+#[tauri::command]
+fn vault_update_entry(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow, 
+    vault: State<Arc<Mutex<Vault>>>,
+    id: Uuid,
+    new: UpdateEntry,
+) -> VaultResult<EntryPublic> {
+    for field in new.fields.iter().flatten() {
+        validate_custom_field(&field.name, &field.kind, field.value.as_ref())?;
+    }
+
+    if let Some(totp) = &new.totp {
+        validate_totp(totp)?;
+    }
+
+    if let Some(mode) = new.url_match {
+        validate_url_patterns(mode, new.url.iter().chain(new.urls.iter().flatten()))?;
+    }
+
+    let result = with_vault(&vault, "E_VAULT_ENTRY_UPDATE", Some(WithVaultOptions::RequireUnlocked), |v| v.update_entry(&id, &new))?;
+    
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Update { 
+        source: webview_window.label().into(), 
+        id: id,
+        new: result.clone()
+    });
+
+    Ok(result)
+}
+
+#[tauri::command]
+fn vault_toggle_favorite(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    id: Uuid
+) -> VaultResult<EntryPublic> {
+    let result = with_vault(&vault, "E_VAULT_ENTRY_FAVORITE", Some(WithVaultOptions::RequireUnlocked), |v| v.toggle_favorite(&id))?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Update { 
+        source: webview_window.label().into(), 
+        id: id,
+        new: result.clone()
+    });
+
+    Ok(result)
+}
+
+#[tauri::command]
+fn vault_delete_entry(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    id: Uuid
+) -> VaultResult<()> {
+    with_vault(&vault, "E_VAULT_ENTRY_DELETE", Some(WithVaultOptions::RequireUnlocked), |v| v.delete_entry(&id))?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Delete { 
+        source: webview_window.label().into(), 
+        id: id
+    });    
+
+    Ok(())
+}
+
+#[tauri::command]
+fn vault_list_trash(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<Vec<EntryPublic>> {
+    with_vault(&vault, "E_VAULT_LIST_TRASH", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.list_trash()))
+}
+
+#[tauri::command]
+fn vault_restore_entry(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    id: Uuid
+) -> VaultResult<EntryPublic> {
+    let result = with_vault(&vault, "E_VAULT_ENTRY_RESTORE", Some(WithVaultOptions::RequireUnlocked), |v| v.restore_entry(&id))?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Restore { 
+        source: webview_window.label().into(), 
+        entry: result.clone()
+    });
+
+    Ok(result)
+}
+
+#[tauri::command]
+fn vault_purge_entry(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>, 
+    id: Uuid
+) -> VaultResult<()> {
+    with_vault(&vault, "E_VAULT_ENTRY_PURGE", Some(WithVaultOptions::RequireUnlocked), |v| {
+        v.purge_entry(&id)?;
+        v.save()
+    })?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Purge { 
+        source: webview_window.label().into(), 
+        id
+    });
+
+    Ok(())
+}
+
+// Import commands
+#[tauri::command]
+fn vault_import(
+    app_handle: tauri::AppHandle,
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>,
+    format: ImportFormat,
+    path: String,
+    password: Option<String>,
+    key_file: Option<String>,
+) -> VaultResult<ImportSummary> {
+    // Parsed before taking the vault, decrypting an export can take a while
+    let parsed = import::parse_file(
+        format,
+        std::path::Path::new(&path),
+        password.as_deref(),
+        key_file.as_deref().map(std::path::Path::new),
+    )?;
+
+    let (entries, summary, folders) = with_vault(&vault, "E_VAULT_IMPORT", Some(WithVaultOptions::RequireUnlocked), |v| {
+        let folder_count = v.get_folders().len();
+        let (entries, summary) = v.import_entries(parsed);
+
+        // Saved once for the whole batch
+        if !entries.is_empty() {
+            v.save()?;
+        }
+
+        let folders = (v.get_folders().len() != folder_count).then(|| v.get_folders());
+        Ok((entries, summary, folders))
+    })?;
+
+    if let Some(folders) = folders {
+        emit_folders(&app_handle, &webview_window, folders);
+    }
+
+    if !entries.is_empty() {
+        let _ = app_handle.emit("vault_changed", VaultChangeEvent::Import {
+            source: webview_window.label().into(),
+            entries
+        });
+    }
 
-        if is_visible {
-            let _ = window.emit("overlay_before_hide", ());
+    Ok(summary)
+}
 
-            tauri::async_runtime::spawn(async move {
-                tokio::time::sleep(Duration::from_millis(100)).await;
+// Export commands
+#[tauri::command]
+fn vault_export(
+    vault: State<Arc<Mutex<Vault>>>,
+    format: ExportFormat,
+    path: String,
+    master_password: String,
+    export_password: Option<String>,
+    key_file: Option<String>,
+) -> VaultResult<usize> {
+    let fields = vec![
+        ValidationField {
+            value: &path,
+            rules: vec![ValidationRule::Required],
+            name: "Path",
+        },
+    ];
 
-                window.hide().unwrap();
-            });
-        } else {
-            window.show().unwrap();
-            let _ = window.emit("overlay_show", ());
-            window.set_focus().unwrap();
-        }
-    }
+    validate(fields)?;
 
-    Ok(())
+    with_vault(&vault, "E_VAULT_EXPORT", Some(WithVaultOptions::RequireUnlocked), |v| {
+        v.export(
+            format,
+            std::path::Path::new(&path),
+            &master_password,
+            export_password.as_deref(),
+            key_file.as_deref().map(std::path::Path::new),
+        )
+    })
 }
 
+// Generator commands
 #[tauri::command]
-fn unlock_overlay(app_handle: tauri::AppHandle, overlay_unlocked: State<Arc<Mutex<bool>>>) -> VaultResult<()> {
-    let mut unlocked = overlay_unlocked
-        .lock()
-        .map_err(|_| VaultError {
-            kind: VaultErrorKind::Access, 
-            severity: VaultErrorSeverity::Blocking, 
-            message: "Overlay lock is not accessible".into(),
-            code: "E_TOGGLE_OVERLAY"
-        })?;
+fn generate_password(
+    vault: State<Arc<Mutex<Vault>>>,
+    options: Option<GeneratorOptions>,
+    preset_id: Option<Uuid>,
+) -> VaultResult<String> {
+    let options = match (options, preset_id) {
+        (Some(options), _) => options,
+        (None, Some(id)) => with_vault(&vault, "E_GENERATE_PASSWORD", None, |v| v.get_generator_preset(&id))?.options,
+        (None, None) => GeneratorOptions::Password(Default::default()),
+    };
 
-    *unlocked = true;
+    generator::generate(&options)
+}
 
-    let _ = app_handle.emit_to("overlay", "vault_ready", ());
+#[tauri::command]
+fn vault_get_generator_presets(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<Vec<GeneratorPreset>> {
+    with_vault(&vault, "E_VAULT_GET_GENERATOR_PRESETS", None, |v| Ok(v.get_generator_presets()))
+}
 
-    Ok(())
+#[tauri::command]
+fn vault_save_generator_preset(vault: State<Arc<Mutex<Vault>>>, preset: GeneratorPreset) -> VaultResult<()> {
+    validate(vec![ValidationField {
+        value: &preset.name,
+        rules: vec![ValidationRule::Required],
+        name: "Preset name",
+    }])?;
+
+    with_vault(&vault, "E_VAULT_SAVE_GENERATOR_PRESET", Some(WithVaultOptions::RequireUnlocked), |v| v.save_generator_preset(preset))
 }
 
-// Vault commands
 #[tauri::command]
-fn vault_setup(vault: State<Arc<Mutex<Vault>>>, master_password: String) -> VaultResult<()> {
-    let setup_result = with_vault(&vault, "E_VAULT_SETUP", None, |v| v.set_master_pw(&master_password))?;
-    Ok(setup_result)
+fn vault_delete_generator_preset(vault: State<Arc<Mutex<Vault>>>, id: Uuid) -> VaultResult<()> {
+    with_vault(&vault, "E_VAULT_DELETE_GENERATOR_PRESET", Some(WithVaultOptions::RequireUnlocked), |v| v.delete_generator_preset(&id))
 }
 
 #[tauri::command]
-fn vault_unlock(app_handle: tauri::AppHandle, vault: State<Arc<Mutex<Vault>>>, overlay_unlocked: State<Arc<Mutex<bool>>>, password: String) -> VaultResult<()> {
-    let unlock_result = with_vault(&vault, "E_VAULT_UNLOCK", None, |v| v.unlock(&password))?;
-    let _ = app_handle.emit_to("main", "vault_changed", VaultChangeEvent::Status);
-    unlock_overlay(app_handle, overlay_unlocked)?;
-    Ok(unlock_result)
+fn vault_get_trash_settings(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<TrashSettings> {
+    with_vault(&vault, "E_VAULT_GET_TRASH_SETTINGS", None, |v| Ok(v.get_trash_settings()))
 }
 
 #[tauri::command]
-fn vault_create_entry(
+fn vault_set_trash_settings(
     app_handle: tauri::AppHandle, 
-    webview_window: tauri::WebviewWindow, 
+    webview_window: tauri::WebviewWindow,
     vault: State<Arc<Mutex<Vault>>>, 
-    entry: vault::Entry
+    settings: TrashSettings
 ) -> VaultResult<()> {
+    // A shorter retention applies right away
+    let purged = with_vault(&vault, "E_VAULT_SET_TRASH_SETTINGS", Some(WithVaultOptions::RequireUnlocked), |v| {
+        v.set_trash_settings(settings)?;
+        Ok(v.purge_expired_trash())
+    })?;
 
-    let fields = vec![
-        ValidationField {
-            value: &entry.label,
-            rules: vec![ValidationRule::Required],
-            name: "Label",
-        },
-        ValidationField {
-            value: &entry.name,
-            rules: vec![ValidationRule::Required],
-            name: "Username",
-        },
-        ValidationField {
-            value: &entry.password,
-            rules: vec![ValidationRule::Required],
-            name: "Password",
-        },
-    ];
+    for id in purged {
+        let _ = app_handle.emit("vault_changed", VaultChangeEvent::Purge { 
+            source: webview_window.label().into(), 
+            id
+        });
+    }
 
-    validate(fields)?;
+    Ok(())
+}
 
-    with_vault(&vault, "E_VAULT_ENTRY_CREATE", Some(WithVaultOptions::RequireUnlocked), |v| {
-        v.new_entry(&entry);
-        v.save()
+#[tauri::command]
+fn vault_create_item(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow, 
+    vault: State<Arc<Mutex<Vault>>>, 
+    item: Item
+) -> VaultResult<()> {
+    validate_item(&item)?;
+
+    let created = with_vault(&vault, "E_VAULT_ITEM_CREATE", Some(WithVaultOptions::RequireUnlocked), |v| {
+        let created = v.new_item(&item);
+        v.save()?;
+        Ok(created)
     })?;
 
     // Notify other windows of change
-    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Create { 
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::ItemCreate { 
         source: webview_window.label().into(), 
-        entry: EntryPublic::from(&entry) 
+        item: created 
     });
 
     Ok(())
 }
 
 #[tauri::command]
-fn vault_get_status(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<VaultStatus> {
-    with_vault(&vault, "E_VAULT_GET_STATUS", None, |v| Ok(v.get_status()))
-}
-
-#[tauri::command]
-fn vault_get_entries(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<Vec<EntryPublic>> {
-    with_vault(&vault, "E_VAULT_GET_ENTRIES", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.get_entries()))
+fn vault_get_items(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<Vec<ItemPublic>> {
+    with_vault(&vault, "E_VAULT_GET_ITEMS", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.get_items()))
 }
 
 #[tauri::command]
-fn vault_get_entry_password(vault: State<Arc<Mutex<Vault>>>, id: Uuid) -> VaultResult<String> {
-    with_vault(&vault, "E_VAULT_GET_PASS", Some(WithVaultOptions::RequireUnlocked), |v| v.get_entry_password(&id))
+fn vault_get_item_secret(vault: State<Arc<Mutex<Vault>>>, id: Uuid, field: String) -> VaultResult<String> {
+    with_vault(&vault, "E_VAULT_GET_ITEM_SECRET", Some(WithVaultOptions::RequireUnlocked), |v| v.get_item_secret(&id, &field))
 }
 
 #[tauri::command]
-fn vault_update_entry(
+fn vault_update_item(
     app_handle: tauri::AppHandle, 
     webview_window: tauri::WebviewWindow, 
     vault: State<Arc<Mutex<Vault>>>,
     id: Uuid,
-    new: UpdateEntry,
-) -> VaultResult<EntryPublic> {
-    let result = with_vault(&vault, "E_VAULT_ENTRY_UPDATE", Some(WithVaultOptions::RequireUnlocked), |v| v.update_entry(&id, &new))?;
-    
+    new: UpdateItem,
+) -> VaultResult<ItemPublic> {
+    let result = with_vault(&vault, "E_VAULT_ITEM_UPDATE", Some(WithVaultOptions::RequireUnlocked), |v| v.update_item(&id, &new, validate_item))?;
+
     // Notify other windows of change
-    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Update { 
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::ItemUpdate { 
         source: webview_window.label().into(), 
-        id: id,
+        id,
         new: result.clone()
     });
 
@@ -257,18 +1237,82 @@ fn vault_update_entry(
 }
 
 #[tauri::command]
-fn vault_toggle_favorite(
+fn vault_delete_item(
     app_handle: tauri::AppHandle, 
     webview_window: tauri::WebviewWindow,
     vault: State<Arc<Mutex<Vault>>>, 
     id: Uuid
-) -> VaultResult<EntryPublic> {
-    let result = with_vault(&vault, "E_VAULT_ENTRY_FAVORITE", Some(WithVaultOptions::RequireUnlocked), |v| v.toggle_favorite(&id))?;
+) -> VaultResult<()> {
+    with_vault(&vault, "E_VAULT_ITEM_DELETE", Some(WithVaultOptions::RequireUnlocked), |v| v.delete_item(&id))?;
 
     // Notify other windows of change
-    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Update { 
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::ItemDelete { 
         source: webview_window.label().into(), 
-        id: id,
+        id
+    });    
+
+    Ok(())
+}
+
+#[tauri::command]
+fn vault_create_note(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow, 
+    vault: State<Arc<Mutex<Vault>>>, 
+    note: SecureNote
+) -> VaultResult<()> {
+    validate(vec![ValidationField {
+        value: &note.label,
+        rules: vec![ValidationRule::Required],
+        name: "Label",
+    }])?;
+
+    with_vault(&vault, "E_VAULT_NOTE_CREATE", Some(WithVaultOptions::RequireUnlocked), |v| {
+        v.new_note(&note);
+        v.save()
+    })?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::NoteCreate { 
+        source: webview_window.label().into(), 
+        note: SecureNotePublic::from(&note) 
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+fn vault_get_notes(vault: State<Arc<Mutex<Vault>>>) -> VaultResult<Vec<SecureNotePublic>> {
+    with_vault(&vault, "E_VAULT_GET_NOTES", Some(WithVaultOptions::RequireUnlocked), |v| Ok(v.get_notes()))
+}
+
+#[tauri::command]
+fn vault_get_note_content(vault: State<Arc<Mutex<Vault>>>, id: Uuid) -> VaultResult<String> {
+    with_vault(&vault, "E_VAULT_GET_NOTE", Some(WithVaultOptions::RequireUnlocked), |v| v.get_note_content(&id))
+}
+
+#[tauri::command]
+fn vault_update_note(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow, 
+    vault: State<Arc<Mutex<Vault>>>,
+    id: Uuid,
+    new: UpdateSecureNote,
+) -> VaultResult<SecureNotePublic> {
+    if let Some(label) = &new.label {
+        validate(vec![ValidationField {
+            value: label,
+            rules: vec![ValidationRule::Required],
+            name: "Label",
+        }])?;
+    }
+
+    let result = with_vault(&vault, "E_VAULT_NOTE_UPDATE", Some(WithVaultOptions::RequireUnlocked), |v| v.update_note(&id, &new))?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::NoteUpdate { 
+        source: webview_window.label().into(), 
+        id,
         new: result.clone()
     });
 
@@ -276,18 +1320,18 @@ fn vault_toggle_favorite(
 }
 
 #[tauri::command]
-fn vault_delete_entry(
+fn vault_delete_note(
     app_handle: tauri::AppHandle, 
     webview_window: tauri::WebviewWindow,
     vault: State<Arc<Mutex<Vault>>>, 
     id: Uuid
 ) -> VaultResult<()> {
-    with_vault(&vault, "E_VAULT_ENTRY_DELETE", Some(WithVaultOptions::RequireUnlocked), |v| v.delete_entry(&id))?;
+    with_vault(&vault, "E_VAULT_NOTE_DELETE", Some(WithVaultOptions::RequireUnlocked), |v| v.delete_note(&id))?;
 
     // Notify other windows of change
-    let _ = app_handle.emit("vault_changed", VaultChangeEvent::Delete { 
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::NoteDelete { 
         source: webview_window.label().into(), 
-        id: id
+        id
     });    
 
     Ok(())
@@ -308,25 +1352,7 @@ fn vault_copy_entry_password(
         ))
     })?;
     
-    let hash = sha256_hash(&text);
-
-    {
-        let mut h = last_hash_state.lock().map_err(|_| VaultError {
-            kind: VaultErrorKind::Access, 
-            severity: VaultErrorSeverity::Blocking, 
-            message: "Clipboard hash not accessible".into(),
-            code: "E_VAULT_COPY_PASS_HASH"
-        })?;
-
-        *h = Some(hash.clone());
-    }
-
-    app_handle.clipboard().write_text(text).map_err(|_| VaultError {
-        kind: VaultErrorKind::Internal, 
-        severity: VaultErrorSeverity::Blocking, 
-        message: "Couldn't copy to clipboard".into(),
-        code: "E_VAULT_COPY_PASS_CLIP"
-    })?;
+    copy_secret(&app_handle, &last_hash_state, text, "E_VAULT_COPY_PASS_CLIP")?;
 
     // Notify other windows of change
     let _ = app_handle.emit("vault_changed", VaultChangeEvent::EntryUse { 
@@ -353,30 +1379,113 @@ fn vault_copy_entry_name(
         ))
     })?;
 
-    let hash = sha256_hash(&text);
+    copy_secret(&app_handle, &last_hash_state, text, "E_VAULT_COPY_NAME_CLIP")?;
 
-    {
-        let mut h = last_hash_state.lock().map_err(|_| VaultError {
-            kind: VaultErrorKind::Access, 
-            severity: VaultErrorSeverity::Blocking, 
-            message: "Clipboard hash not accessible".into(),
-            code: "E_VAULT_COPY_PASS_HASH"
-        })?;
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::EntryUse { 
+        source: webview_window.label().into(), 
+        id: id,
+        result: use_result.clone()
+    }); 
 
-        *h = Some(hash.clone());
-    }
+    Ok(use_result)
+}
 
-    app_handle.clipboard().write_text(text).map_err(|_| VaultError {
-        kind: VaultErrorKind::Internal, 
-        severity: VaultErrorSeverity::Blocking, 
-        message: "Couldn't copy to clipboard".into(),
-        code: "E_VAULT_COPY_NAME_CLIP"
+#[tauri::command]
+fn vault_copy_entry_field(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>,
+    last_hash_state: State<Arc<Mutex<Option<String>>>>,
+    id: Uuid,
+    field_id: Uuid
+) -> VaultResult<EntryUseResult> {
+    let (text, use_result) = with_vault(&vault, "E_VAULT_COPY_FIELD", Some(WithVaultOptions::RequireUnlocked), |v| {
+        Ok((
+            v.get_entry_field(&id, &field_id)?,
+            v.use_entry(&id)?
+        ))
     })?;
 
+    copy_secret(&app_handle, &last_hash_state, text, "E_VAULT_COPY_FIELD_CLIP")?;
+
     // Notify other windows of change
     let _ = app_handle.emit("vault_changed", VaultChangeEvent::EntryUse { 
         source: webview_window.label().into(), 
-        id: id,
+        id,
+        result: use_result.clone()
+    }); 
+
+    Ok(use_result)
+}
+
+#[tauri::command]
+fn vault_copy_item_secret(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>,
+    last_hash_state: State<Arc<Mutex<Option<String>>>>,
+    id: Uuid,
+    field: String
+) -> VaultResult<EntryUseResult> {
+    let (text, use_result) = with_vault(&vault, "E_VAULT_COPY_ITEM_SECRET", Some(WithVaultOptions::RequireUnlocked), |v| {
+        Ok((
+            v.get_item_secret(&id, &field)?,
+            v.use_entry(&id)?
+        ))
+    })?;
+
+    copy_secret(&app_handle, &last_hash_state, text, "E_VAULT_COPY_ITEM_SECRET_CLIP")?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::EntryUse { 
+        source: webview_window.label().into(), 
+        id,
+        result: use_result.clone()
+    }); 
+
+    Ok(use_result)
+}
+
+#[tauri::command]
+fn vault_copy_history_password(
+    app_handle: tauri::AppHandle, 
+    vault: State<Arc<Mutex<Vault>>>,
+    last_hash_state: State<Arc<Mutex<Option<String>>>>,
+    id: Uuid,
+    history_id: Uuid
+) -> VaultResult<()> {
+    let text = with_vault(&vault, "E_VAULT_COPY_HISTORY", Some(WithVaultOptions::RequireUnlocked), |v| v.get_history_password(&id, &history_id))?;
+
+    copy_secret(&app_handle, &last_hash_state, text, "E_VAULT_COPY_HISTORY_CLIP")
+}
+
+#[tauri::command]
+fn vault_get_totp_code(vault: State<Arc<Mutex<Vault>>>, id: Uuid) -> VaultResult<TotpCode> {
+    with_vault(&vault, "E_VAULT_GET_TOTP", Some(WithVaultOptions::RequireUnlocked), |v| v.get_totp_code(&id))
+}
+
+#[tauri::command]
+fn vault_copy_entry_totp(
+    app_handle: tauri::AppHandle, 
+    webview_window: tauri::WebviewWindow,
+    vault: State<Arc<Mutex<Vault>>>,
+    last_hash_state: State<Arc<Mutex<Option<String>>>>,
+    id: Uuid
+) -> VaultResult<EntryUseResult> {
+    let (totp, use_result) = with_vault(&vault, "E_VAULT_COPY_TOTP", Some(WithVaultOptions::RequireUnlocked), |v| {
+        Ok((
+            v.get_totp_code(&id)?,
+            v.use_entry(&id)?
+        ))
+    })?;
+
+    copy_secret(&app_handle, &last_hash_state, totp.code, "E_VAULT_COPY_TOTP_CLIP")?;
+
+    // Notify other windows of change
+    let _ = app_handle.emit("vault_changed", VaultChangeEvent::EntryUse { 
+        source: webview_window.label().into(), 
+        id,
         result: use_result.clone()
     }); 
 
@@ -449,6 +1558,8 @@ pub fn run() {
                 v.load();
             }
 
+            spawn_auto_lock_watcher(app.handle().clone());
+
             Ok(())
         })
         .plugin(tauri_plugin_single_instance::init(|app, _, _| {
@@ -465,16 +1576,72 @@ pub fn run() {
             unlock_overlay,
             vault_setup,
             vault_unlock,
+            vault_lock,
+            vault_get_unlock_throttle,
+            vault_get_auto_lock_settings,
+            vault_set_auto_lock_settings,
             vault_create_entry,
             vault_copy_entry_password,
             vault_copy_entry_name,
+            vault_copy_entry_field,
+            vault_get_totp_code,
+            vault_copy_entry_totp,
+            vault_get_entry_notes,
+            vault_get_password_history,
+            vault_check_password_reuse,
+            vault_audit_breaches,
+            vault_security_report,
+            vault_restore_password,
+            vault_purge_password_history,
+            vault_copy_history_password,
+            vault_search,
+            vault_find_entries_for_url,
+            vault_get_tags,
+            vault_assign_tags,
+            vault_move_entries,
+            vault_get_folders,
+            vault_create_folder,
+            vault_rename_folder,
+            vault_move_folder,
+            vault_delete_folder,
+            vault_list_trash,
+            vault_restore_entry,
+            vault_purge_entry,
+            vault_get_trash_settings,
+            vault_import,
+            vault_export,
+            generate_password,
+            vault_get_generator_presets,
+            vault_save_generator_preset,
+            vault_delete_generator_preset,
+            vault_set_trash_settings,
+            vault_create_item,
+            vault_get_items,
+            vault_get_item_secret,
+            vault_update_item,
+            vault_delete_item,
+            vault_copy_item_secret,
+            vault_create_note,
+            vault_get_notes,
+            vault_get_note_content,
+            vault_update_note,
+            vault_delete_note,
             vault_clear_clipboard_safe,
             vault_get_status,
             vault_get_entries,
             vault_get_entry_password,
             vault_update_entry,
             vault_toggle_favorite,
-            vault_delete_entry
+            vault_delete_entry,
+            vault_change_master_password,
+            vault_rotate_dek,
+            vault_get_kdf_params,
+            vault_calibrate_kdf,
+            vault_list_backups,
+            vault_inspect_backup,
+            vault_restore_backup,
+            vault_get_backup_policy,
+            vault_set_backup_policy
         ])
         .on_window_event(|window, event| {
             if window.label() == "main" {
@@ -0,0 +1,397 @@
+use crate::vault::entry::{Entry, PasswordStrength};
+use crate::vault::url_match;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use argon2::password_hash::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+use zeroize::Zeroize;
+
+const HASH_LENGTH: usize = 40;
+// Range files are named after the first five characters of the hash
+const PREFIX_LENGTH: usize = 5;
+// Below this many bytes the rest of the search reads lines in order
+const SCAN_WINDOW: u64 = 4096;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BreachResult {
+    pub id: Uuid,
+    // Times the password shows up in breaches, 0 when it wasn't found
+    pub count: u64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditProgress {
+    pub checked: usize,
+    pub total: usize,
+}
+
+/// Entries sharing a password. The id is a truncated keyed hash that only
+/// tells groups apart within one report.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReusedPassword {
+    pub id: String,
+    pub entries: Vec<Uuid>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityReport {
+    pub total: usize,
+    pub reused: Vec<ReusedPassword>,
+    pub weak: Vec<Uuid>,
+    // Not modified for longer than the age threshold
+    pub old: Vec<Uuid>,
+    // At least one URL uses plain http
+    pub insecure_urls: Vec<Uuid>,
+    // Groups of entries with the same label and username
+    pub duplicates: Vec<Vec<Uuid>>,
+}
+
+/// Uppercase hex SHA-1, the form used by the pwned passwords files.
+pub fn sha1_hex(password: &str) -> String {
+    hex::encode_upper(Sha1::digest(password.as_bytes()))
+}
+
+fn audit_error(kind: VaultErrorKind, message: String) -> VaultError {
+    VaultError {
+        kind,
+        severity: VaultErrorSeverity::Soft,
+        message,
+        code: "E_AUDIT_BREACHES",
+    }
+}
+
+/// Splits a `HASH:COUNT` line, the hash is uppercased for comparison.
+fn parse_line(line: &str) -> Option<(String, u64)> {
+    let (hash, count) = line.trim().split_once(':')?;
+    Some((hash.to_ascii_uppercase(), count.trim().parse().ok()?))
+}
+
+/// A downloaded pwned passwords list, either the single file ordered by
+/// hash or a directory with one file per five character hash prefix.
+/// Neither is read into memory as a whole.
+pub enum HashSource {
+    Ordered { reader: BufReader<File>, len: u64 },
+    Ranges(PathBuf),
+}
+
+impl HashSource {
+    pub fn open(path: &Path) -> VaultResult<Self> {
+        if path.is_dir() {
+            return Ok(HashSource::Ranges(path.to_path_buf()));
+        }
+
+        let file = File::open(path).map_err(|e| audit_error(VaultErrorKind::IO, format!("Couldn't open hash file: {}", e)))?;
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut reader = BufReader::new(file);
+
+        // Catches the NTLM variant and unrelated files before searching them
+        let mut first = String::new();
+        reader
+            .read_line(&mut first)
+            .map_err(|e| audit_error(VaultErrorKind::IO, format!("Couldn't read hash file: {}", e)))?;
+
+        match parse_line(&first) {
+            Some((hash, _)) if hash.len() == HASH_LENGTH => Ok(HashSource::Ordered { reader, len }),
+            _ => Err(audit_error(
+                VaultErrorKind::Parse,
+                "Not a SHA-1 pwned passwords file, expected lines of HASH:COUNT ordered by hash".into(),
+            )),
+        }
+    }
+
+    /// Breach count of an uppercase SHA-1 hex hash.
+    pub fn lookup(&mut self, hash: &str) -> io::Result<u64> {
+        match self {
+            HashSource::Ordered { reader, len } => search_ordered(reader, *len, hash),
+            HashSource::Ranges(dir) => search_range(dir, hash),
+        }
+    }
+}
+
+/// Reads the line starting at the reader position, returns it with its length in bytes.
+fn next_line(reader: &mut BufReader<File>) -> io::Result<Option<(String, u64)>> {
+    let mut line = String::new();
+    match reader.read_line(&mut line)? {
+        0 => Ok(None),
+        read => Ok(Some((line, read as u64))),
+    }
+}
+
+/// Binary search over byte offsets. `lo` always sits at the start of a line
+/// and the line holding the hash, if there is one, starts before `hi`.
+fn search_ordered(reader: &mut BufReader<File>, len: u64, hash: &str) -> io::Result<u64> {
+    let (mut lo, mut hi) = (0, len);
+
+    while hi - lo > SCAN_WINDOW {
+        let mid = lo + (hi - lo) / 2;
+        reader.seek(SeekFrom::Start(mid))?;
+
+        // Skip the rest of the line `mid` landed in
+        let skipped = next_line(reader)?.map_or(0, |(_, n)| n);
+        let start = mid + skipped;
+
+        let Some((line, read)) = next_line(reader)?.filter(|_| start < hi) else {
+            hi = mid;
+            continue;
+        };
+
+        match parse_line(&line) {
+            Some((found, count)) if found == hash => return Ok(count),
+            Some((found, _)) if found.as_str() < hash => lo = start + read,
+            _ => hi = start,
+        }
+    }
+
+    reader.seek(SeekFrom::Start(lo))?;
+    let mut position = lo;
+
+    while position < hi {
+        let Some((line, read)) = next_line(reader)? else {
+            break;
+        };
+        position += read;
+
+        match parse_line(&line) {
+            Some((found, count)) if found == hash => return Ok(count),
+            Some((found, _)) if found.as_str() > hash => break,
+            _ => {}
+        }
+    }
+
+    Ok(0)
+}
+
+/// Range files only hold the part of the hash after the prefix.
+fn search_range(dir: &Path, hash: &str) -> io::Result<u64> {
+    let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+
+    let file = match File::open(dir.join(format!("{}.txt", prefix))) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    for line in BufReader::new(file).lines() {
+        if let Some((found, count)) = parse_line(&line?) {
+            if found == suffix {
+                return Ok(count);
+            }
+        }
+    }
+
+    Ok(0)
+}
+
+/// Looks up every hash in the list at `path`. Entries sharing a password
+/// are only searched for once, `progress` is called after every entry.
+pub fn check_breaches(
+    path: &Path,
+    hashes: &[(Uuid, String)],
+    mut progress: impl FnMut(AuditProgress),
+) -> VaultResult<Vec<BreachResult>> {
+    let mut source = HashSource::open(path)?;
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    let mut results = Vec::with_capacity(hashes.len());
+
+    for (checked, (id, hash)) in hashes.iter().enumerate() {
+        let count = match counts.get(hash.as_str()) {
+            Some(count) => *count,
+            None => {
+                let count = source
+                    .lookup(hash)
+                    .map_err(|e| audit_error(VaultErrorKind::IO, format!("Couldn't read hash file: {}", e)))?;
+                counts.insert(hash, count);
+                count
+            }
+        };
+
+        results.push(BreachResult { id: *id, count });
+        progress(AuditProgress {
+            checked: checked + 1,
+            total: hashes.len(),
+        });
+    }
+
+    Ok(results)
+}
+
+/// Groups of at least two ids sharing a key, in order of first appearance.
+fn groups<K: std::hash::Hash + Eq + Clone>(items: impl Iterator<Item = (K, Uuid)>) -> Vec<(K, Vec<Uuid>)> {
+    let mut groups: Vec<(K, Vec<Uuid>)> = Vec::new();
+    let mut index: HashMap<K, usize> = HashMap::new();
+
+    for (key, id) in items {
+        match index.get(&key) {
+            Some(i) => groups[*i].1.push(id),
+            None => {
+                index.insert(key.clone(), groups.len());
+                groups.push((key, vec![id]));
+            }
+        }
+    }
+
+    groups.retain(|(_, ids)| ids.len() > 1);
+    groups
+}
+
+/// Audits the given logins. Reused passwords are compared through an HMAC
+/// with a key that only lives for this call, so neither the passwords nor
+/// stable hashes of them leave this function. Previous passwords count
+/// towards reuse like current ones.
+pub fn security_report(entries: &[&Entry], max_age_days: u32) -> SecurityReport {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+
+    let keyed_hash = |password: &str| {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("HMAC accepts any key length");
+        mac.update(password.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    };
+
+    // Each entry counts once per password, however often it used it
+    let mut hashes = Vec::new();
+    for entry in entries {
+        let mut seen = HashSet::new();
+
+        for hash in entry.known_passwords().map(|p| keyed_hash(p)) {
+            if seen.insert(hash.clone()) {
+                hashes.push((hash, entry.id));
+            }
+        }
+    }
+
+    let reused = groups(hashes.into_iter())
+        .into_iter()
+        .map(|(hash, entries)| ReusedPassword {
+            id: hex::encode(&hash[..8]),
+            entries,
+        })
+        .collect();
+
+    key.zeroize();
+
+    let cutoff = OffsetDateTime::now_utc() - Duration::days(max_age_days.into());
+
+    let insecure = |e: &Entry| {
+        e.all_urls()
+            .filter_map(|u| url_match::parse(u))
+            .any(|u| u.scheme() == "http")
+    };
+
+    let duplicates = groups(entries.iter().map(|e| {
+        ((e.label.trim().to_lowercase(), e.name.trim().to_lowercase()), e.id)
+    }))
+    .into_iter()
+    .map(|(_, ids)| ids)
+    .collect();
+
+    SecurityReport {
+        total: entries.len(),
+        reused,
+        weak: entries
+            .iter()
+            .filter(|e| matches!(e.password_strength, PasswordStrength::Weak))
+            .map(|e| e.id)
+            .collect(),
+        old: entries.iter().filter(|e| e.modified_at < cutoff).map(|e| e.id).collect(),
+        insecure_urls: entries.iter().filter(|e| insecure(e)).map(|e| e.id).collect(),
+        duplicates,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Sorted hashes of made-up passwords, enough to take the list well past `SCAN_WINDOW`.
+    fn sorted_hashes(n: usize) -> Vec<String> {
+        let mut hashes: Vec<String> = (0..n).map(|i| sha1_hex(&format!("password{}", i))).collect();
+        hashes.sort();
+        hashes
+    }
+
+    fn write_list(path: &Path, lines: &[(String, u64)], newline: &str, trailing: bool) {
+        let mut content = lines
+            .iter()
+            .map(|(hash, count)| format!("{}:{}", hash, count))
+            .collect::<Vec<_>>()
+            .join(newline);
+        if trailing {
+            content.push_str(newline);
+        }
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn ordered_file_finds_every_hash() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("pwned.txt");
+        let lines: Vec<(String, u64)> = sorted_hashes(2000).into_iter().zip(1..).collect();
+
+        for (newline, trailing) in [("\n", true), ("\r\n", true), ("\n", false)] {
+            write_list(&path, &lines, newline, trailing);
+            let mut source = HashSource::open(&path).unwrap();
+
+            assert_eq!(source.lookup(&lines[0].0).unwrap(), 1);
+            assert_eq!(source.lookup(&lines[1999].0).unwrap(), 2000);
+
+            for (hash, count) in &lines {
+                assert_eq!(source.lookup(hash).unwrap(), *count, "{:?} {}", newline, hash);
+            }
+        }
+    }
+
+    #[test]
+    fn ordered_file_misses_unknown_hashes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("pwned.txt");
+        let lines: Vec<(String, u64)> = sorted_hashes(2000).into_iter().zip(1..).collect();
+
+        for newline in ["\n", "\r\n"] {
+            write_list(&path, &lines, newline, true);
+            let mut source = HashSource::open(&path).unwrap();
+
+            // Before the first line, after the last one and somewhere in between
+            for hash in ["0".repeat(40), "F".repeat(40), sha1_hex("not in the list")] {
+                assert_eq!(source.lookup(&hash).unwrap(), 0, "{:?} {}", newline, hash);
+            }
+        }
+    }
+
+    #[test]
+    fn ordered_file_finds_hash_on_line_starting_at_midpoint() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("pwned.txt");
+
+        // Equal length lines, so the first midpoint is exactly the start of line 500
+        let hashes = sorted_hashes(1000);
+        let lines: Vec<(String, u64)> = hashes.iter().map(|h| (h.clone(), 1234567)).collect();
+
+        for newline in ["\n", "\r\n"] {
+            write_list(&path, &lines, newline, true);
+            let line_length = (HASH_LENGTH + ":1234567".len() + newline.len()) as u64;
+            let len = fs::metadata(&path).unwrap().len();
+            assert_eq!(len / 2, 500 * line_length);
+
+            let mut source = HashSource::open(&path).unwrap();
+            for hash in &hashes[499..=501] {
+                assert_eq!(source.lookup(hash).unwrap(), 1234567, "{:?} {}", newline, hash);
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::vault::storage;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const SETTINGS_FILE: &str = "autolock.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoLockSettings {
+    /// Seconds without any vault command before locking, 0 disables the timer.
+    pub idle_timeout_secs: u64,
+    pub lock_on_sleep: bool,
+}
+
+impl Default for AutoLockSettings {
+    fn default() -> Self {
+        AutoLockSettings {
+            idle_timeout_secs: 5 * 60,
+            lock_on_sleep: true,
+        }
+    }
+}
+
+/// Tracks vault activity against the configured idle timeout. The actual
+/// locking is driven by a watcher task on the application side.
+pub struct AutoLock {
+    path: PathBuf,
+    settings: AutoLockSettings,
+    last_activity: Instant,
+}
+
+impl Default for AutoLock {
+    fn default() -> Self {
+        AutoLock {
+            path: PathBuf::new(),
+            settings: AutoLockSettings::default(),
+            last_activity: Instant::now(),
+        }
+    }
+}
+
+impl AutoLock {
+    /// Settings live next to the vault file, since they are needed while locked.
+    pub fn set_vault_path(&mut self, vault_path: &Path) {
+        self.path = vault_path.with_file_name(SETTINGS_FILE);
+
+        self.settings = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn get_settings(&self) -> AutoLockSettings {
+        self.settings.clone()
+    }
+
+    pub fn set_settings(&mut self, settings: AutoLockSettings) -> VaultResult<()> {
+        let content = serde_json::to_string_pretty(&settings).map_err(|e| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Serialization failed: {e}"),
+            code: "E_AUTOLOCK_SETTINGS",
+        })?;
+
+        storage::write_atomic(&self.path, content.as_bytes()).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Couldn't save auto-lock settings: {e}"),
+            code: "E_AUTOLOCK_SETTINGS",
+        })?;
+
+        self.settings = settings;
+        self.touch();
+
+        Ok(())
+    }
+
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.settings.idle_timeout_secs > 0
+            && self.last_activity.elapsed() >= Duration::from_secs(self.settings.idle_timeout_secs)
+    }
+}
@@ -0,0 +1,236 @@
+use crate::vault::storage;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+const BACKUP_PREFIX: &str = "vault-";
+const BACKUP_EXTENSION: &str = ".json";
+const POLICY_FILE: &str = "policy.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupPolicy {
+    /// How many previous versions of the vault file to keep, 0 disables backups.
+    pub max_backups: usize,
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        BackupPolicy { max_backups: 10 }
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub id: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub size: u64,
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupDetails {
+    pub info: BackupInfo,
+    pub entry_count: usize,
+}
+
+/// Keeps rotating copies of the vault file in a `backups` folder next to it.
+/// Backups are plain copies of the encrypted save file, so they stay encrypted
+/// with whatever master password was in use when they were taken.
+#[derive(Default)]
+pub struct BackupManager {
+    dir: PathBuf,
+    policy: BackupPolicy,
+}
+
+impl BackupManager {
+    /// Points the manager at the backup folder belonging to `vault_path`
+    /// and picks up the stored retention policy.
+    pub fn set_vault_path(&mut self, vault_path: &Path) {
+        self.dir = vault_path
+            .parent()
+            .map(|p| p.join("backups"))
+            .unwrap_or_else(|| PathBuf::from("backups"));
+
+        self.policy = std::fs::read_to_string(self.dir.join(POLICY_FILE))
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn get_policy(&self) -> BackupPolicy {
+        self.policy.clone()
+    }
+
+    pub fn set_policy(&mut self, policy: BackupPolicy) -> VaultResult<()> {
+        self.ensure_dir()?;
+
+        let content = serde_json::to_string_pretty(&policy).map_err(|e| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Serialization failed: {e}"),
+            code: "E_BACKUP_POLICY",
+        })?;
+
+        storage::write_atomic(&self.dir.join(POLICY_FILE), content.as_bytes()).map_err(|e| {
+            VaultError {
+                kind: VaultErrorKind::IO,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Couldn't save backup policy: {e}"),
+                code: "E_BACKUP_POLICY",
+            }
+        })?;
+
+        self.policy = policy;
+        self.prune()
+    }
+
+    fn ensure_dir(&self) -> VaultResult<()> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Couldn't create backup directory: {e}"),
+            code: "E_BACKUP_DIR",
+        })
+    }
+
+    /// Copies the current vault file into the backup folder, then drops the
+    /// oldest copies that exceed the retention policy.
+    pub fn snapshot(&self, vault_path: &Path) -> VaultResult<()> {
+        if self.policy.max_backups == 0 {
+            return Ok(());
+        }
+
+        self.force_snapshot(vault_path)
+    }
+
+    /// Like `snapshot`, but also taken when backups are disabled, for changes
+    /// that have to stay undoable. Such a copy is only pruned once backups
+    /// are turned back on.
+    pub fn force_snapshot(&self, vault_path: &Path) -> VaultResult<()> {
+        if !vault_path.exists() {
+            return Ok(());
+        }
+
+        self.ensure_dir()?;
+
+        let content = std::fs::read(vault_path).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Couldn't read vault file: {e}"),
+            code: "E_BACKUP_SNAPSHOT",
+        })?;
+
+        let millis = OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000;
+        let mut path = self.backup_path(&format!("{}{:013}", BACKUP_PREFIX, millis));
+
+        // Two saves within the same millisecond shouldn't clobber each other
+        let mut suffix = 1;
+        while path.exists() {
+            path = self.backup_path(&format!("{}{:013}-{}", BACKUP_PREFIX, millis, suffix));
+            suffix += 1;
+        }
+
+        storage::write_atomic(&path, &content).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Couldn't write backup: {e}"),
+            code: "E_BACKUP_SNAPSHOT",
+        })?;
+
+        if self.policy.max_backups == 0 {
+            return Ok(());
+        }
+
+        self.prune()
+    }
+
+    fn backup_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}{}", id, BACKUP_EXTENSION))
+    }
+
+    fn prune(&self) -> VaultResult<()> {
+        let backups = self.list()?;
+
+        for backup in backups.iter().skip(self.policy.max_backups) {
+            std::fs::remove_file(self.backup_path(&backup.id)).map_err(|e| VaultError {
+                kind: VaultErrorKind::IO,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Couldn't remove old backup: {e}"),
+                code: "E_BACKUP_PRUNE",
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Lists the available backups, newest first.
+    pub fn list(&self) -> VaultResult<Vec<BackupInfo>> {
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let mut backups: Vec<BackupInfo> = dir
+            .filter_map(|item| item.ok())
+            .filter_map(|item| {
+                let name = item.file_name().to_string_lossy().to_string();
+                let id = name.strip_suffix(BACKUP_EXTENSION)?;
+                let millis = id
+                    .strip_prefix(BACKUP_PREFIX)?
+                    .split('-')
+                    .next()?
+                    .parse::<i128>()
+                    .ok()?;
+
+                let created_at =
+                    OffsetDateTime::from_unix_timestamp_nanos(millis * 1_000_000).ok()?;
+
+                let version = std::fs::read_to_string(item.path())
+                    .ok()
+                    .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+                    .and_then(|v| v["version"].as_str().map(String::from));
+
+                Some(BackupInfo {
+                    id: id.to_string(),
+                    created_at,
+                    size: item.metadata().map(|m| m.len()).unwrap_or(0),
+                    version,
+                })
+            })
+            .collect();
+
+        backups.sort_by(|a, b| b.id.cmp(&a.id));
+
+        Ok(backups)
+    }
+
+    pub fn get(&self, id: &str) -> VaultResult<BackupInfo> {
+        self.list()?
+            .into_iter()
+            .find(|b| b.id == id)
+            .ok_or_else(|| VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Backup '{}' not found", id),
+                code: "E_BACKUP_NOT_FOUND",
+            })
+    }
+
+    /// Reads the raw save file of a backup. Only ids returned by `list` are
+    /// accepted, so arbitrary paths can't be smuggled in.
+    pub fn read(&self, id: &str) -> VaultResult<String> {
+        let info = self.get(id)?;
+
+        std::fs::read_to_string(self.backup_path(&info.id)).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Couldn't read backup: {e}"),
+            code: "E_BACKUP_READ",
+        })
+    }
+}
@@ -1,34 +1,152 @@
-use argon2::Argon2;
+use argon2::{Algorithm, Argon2, Params, Version};
 use chacha20poly1305::{
-    aead::{Aead, OsRng},
+    aead::{Aead, OsRng, Payload},
     AeadCore, Key, KeyInit, XChaCha20Poly1305, XNonce,
 };
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+
+/// Upper bounds for calibration, so a fast machine can't produce a vault
+/// that a slower one is unable to open.
+const CALIBRATION_MAX_M_COST: u32 = 256 * 1024;
+const CALIBRATION_MAX_T_COST: u32 = 16;
+
+/// Upper bounds for Argon2 settings read from files we didn't write, checked
+/// before deriving so a crafted file can't allocate or run without limit.
+/// Memory is in KiB, work is memory times passes.
+const FOREIGN_MAX_M_COST: u32 = 1024 * 1024;
+const FOREIGN_MAX_WORK: u64 = 16 * 1024 * 1024;
+const FOREIGN_MAX_P_COST: u32 = 64;
+
+/// Key derivation settings, stored in the vault header so they can be
+/// strengthened later without locking out existing vaults.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct KdfParams {
+    pub algorithm: String,
+    pub version: u32,
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl KdfParams {
+    /// What `Argon2::default()` used, which is how every vault before
+    /// the parameters were stored was derived.
+    pub fn legacy() -> Self {
+        KdfParams {
+            algorithm: Algorithm::Argon2id.as_str().into(),
+            version: Version::V0x13.into(),
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+
+    fn to_argon2(&self) -> Result<Argon2<'static>, String> {
+        let algorithm = Algorithm::new(&self.algorithm).map_err(|e| e.to_string())?;
+        let version = Version::try_from(self.version).map_err(|e| e.to_string())?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| e.to_string())?;
+
+        Ok(Argon2::new(algorithm, version, params))
+    }
+}
+
+/// Refuses Argon2 costs beyond what any sane export or database asks for.
+pub fn check_argon2_costs(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<(), String> {
+    if m_cost > FOREIGN_MAX_M_COST {
+        return Err(format!("{} KiB of memory is more than the {} KiB allowed", m_cost, FOREIGN_MAX_M_COST));
+    }
+    if p_cost > FOREIGN_MAX_P_COST {
+        return Err(format!("{} lanes are more than the {} allowed", p_cost, FOREIGN_MAX_P_COST));
+    }
+    if u64::from(m_cost) * u64::from(t_cost) > FOREIGN_MAX_WORK {
+        return Err(format!("{} passes over {} KiB take too long to derive", t_cost, m_cost));
+    }
+
+    Ok(())
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::legacy()
+    }
+}
 
 pub struct VaultCryptoManager;
 
 impl VaultCryptoManager {
-    pub fn derive_kek(password: &[u8], salt: &[u8]) -> Result<[u8; 32], String> {
+    pub fn derive_kek(password: &[u8], salt: &[u8], params: &KdfParams) -> Result<[u8; 32], String> {
         let mut kek = [0u8; 32];
-        Argon2::default()
+        params
+            .to_argon2()?
             .hash_password_into(password, salt, &mut kek)
             .map_err(|e| e.to_string())?;
 
         Ok(kek)
     }
 
-    pub fn wrap_dek(dek: &[u8], kek: &[u8]) -> Result<(Vec<u8>, XNonce), String> {
+    /// Finds the cheapest parameters (never weaker than the defaults) that take
+    /// at least `target` to derive a key on this machine. Memory is raised first
+    /// since it is what hurts GPU attackers most, then the iteration count.
+    pub fn calibrate_kdf(target: Duration) -> Result<KdfParams, String> {
+        let mut params = KdfParams::default();
+        let salt = [0u8; 32];
+
+        loop {
+            let start = Instant::now();
+            Self::derive_kek(b"calibration", &salt, &params)?;
+            let elapsed = start.elapsed();
+
+            if elapsed >= target {
+                return Ok(params);
+            }
+
+            if params.m_cost < CALIBRATION_MAX_M_COST {
+                params.m_cost = (params.m_cost * 2).min(CALIBRATION_MAX_M_COST);
+            } else if params.t_cost < CALIBRATION_MAX_T_COST {
+                params.t_cost += 1;
+            } else {
+                return Ok(params);
+            }
+        }
+    }
+
+    /// Canonical encoding of the vault header, authenticated as associated data
+    /// by both the DEK wrap and the vault payload.
+    pub fn header_aad(version: &str, vault_id: &str, kdf: &KdfParams) -> Vec<u8> {
+        format!(
+            "nebula-vault;version={};id={};kdf={},{},{},{},{}",
+            version, vault_id, kdf.algorithm, kdf.version, kdf.m_cost, kdf.t_cost, kdf.p_cost
+        )
+        .into_bytes()
+    }
+
+    /// Fingerprint of the KEK that doesn't depend on the header, so a failed
+    /// unwrap can be told apart as a wrong password or a modified header.
+    pub fn key_check(kek: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"nebula-key-check");
+        hasher.update(kek);
+        hasher.finalize().into()
+    }
+
+    pub fn wrap_dek(dek: &[u8], kek: &[u8], aad: &[u8]) -> Result<(Vec<u8>, XNonce), String> {
         let cipher = XChaCha20Poly1305::new(Key::from_slice(kek));
         let dek_nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
-        let wrapped_dek = cipher.encrypt(&dek_nonce, dek).map_err(|e| e.to_string())?;
+        let wrapped_dek = cipher
+            .encrypt(&dek_nonce, Payload { msg: dek, aad })
+            .map_err(|e| e.to_string())?;
 
         Ok((wrapped_dek, dek_nonce))
     }
 
-    pub fn unwrap_dek(wrapped_dek: &[u8], kek: &[u8], nonce: &XNonce) -> Result<[u8; 32], String> {
+    pub fn unwrap_dek(wrapped_dek: &[u8], kek: &[u8], nonce: &XNonce, aad: &[u8]) -> Result<[u8; 32], String> {
         let cipher = XChaCha20Poly1305::new(Key::from_slice(&kek));
 
         let dek_vec = cipher
-            .decrypt(&nonce, wrapped_dek)
+            .decrypt(&nonce, Payload { msg: wrapped_dek, aad })
             .map_err(|e| e.to_string())?;
 
         Ok(dek_vec
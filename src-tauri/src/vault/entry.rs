@@ -2,14 +2,94 @@ use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Clone)]
+/// Previous passwords kept per entry, oldest ones are dropped first.
+pub const MAX_PASSWORD_HISTORY: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub enum PasswordStrength {
+    #[default]
     Weak,
     Okay,
     Strong,
     Excellent
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub enum CustomFieldKind {
+    Text,
+    Hidden,
+    Url,
+    Email,
+    Number,
+    Date,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomField {
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    pub name: String,
+    pub kind: CustomFieldKind,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomFieldPublic {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: CustomFieldKind,
+    // Withheld for hidden fields, those are only fetched on demand
+    pub value: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCustomField {
+    // Missing for newly added fields
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub kind: CustomFieldKind,
+    // Missing to keep the current value of an existing field
+    pub value: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordHistoryEntry {
+    pub id: Uuid,
+    pub password: String,
+    pub password_strength: PasswordStrength,
+    // When this password was replaced
+    #[serde(with = "time::serde::rfc3339")]
+    pub replaced_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordHistoryInfo {
+    pub id: Uuid,
+    pub password_strength: PasswordStrength,
+    #[serde(with = "time::serde::rfc3339")]
+    pub replaced_at: OffsetDateTime,
+}
+
+/// How an entry's URLs are compared to the URL of a site.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum UrlMatchMode {
+    // Same registrable domain, `login.example.co.uk` matches `example.co.uk`
+    #[default]
+    BaseDomain,
+    // Same host and explicit port, any scheme
+    Host,
+    // The site URL starts with the entry URL
+    StartsWith,
+    // The entry URLs are case-insensitive regular expressions
+    RegEx,
+    Never,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Entry {
@@ -25,8 +105,32 @@ pub struct Entry {
     pub url: String,
     pub name: String,
     pub password: String,
+    // Scored by the vault, a value sent by the client is replaced
+    #[serde(default)]
     pub password_strength: PasswordStrength,
     pub favorite: bool,
+    #[serde(default)]
+    pub fields: Vec<CustomField>,
+    #[serde(default)]
+    pub notes: String,
+    // Raw base32 secret or otpauth:// URI
+    #[serde(default)]
+    pub totp: Option<String>,
+    // Newest first
+    #[serde(default)]
+    pub password_history: Vec<PasswordHistoryEntry>,
+    // Set while the entry is in the trash
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub deleted_at: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub folder_id: Option<Uuid>,
+    // Matched along with `url`
+    #[serde(default)]
+    pub urls: Vec<String>,
+    #[serde(default)]
+    pub url_match: UrlMatchMode,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -45,6 +149,15 @@ pub struct EntryPublic {
     pub name: String,
     pub password_strength: PasswordStrength,
     pub favorite: bool,
+    pub fields: Vec<CustomFieldPublic>,
+    pub has_totp: bool,
+    pub history_count: usize,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub deleted_at: Option<OffsetDateTime>,
+    pub tags: Vec<String>,
+    pub folder_id: Option<Uuid>,
+    pub urls: Vec<String>,
+    pub url_match: UrlMatchMode,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -54,7 +167,14 @@ pub struct UpdateEntry {
     pub url: Option<String>,
     pub name: Option<String>,
     pub password: Option<String>,
-    pub password_strength: Option<PasswordStrength>
+    // The complete new list of custom fields, in order
+    pub fields: Option<Vec<UpdateCustomField>>,
+    pub notes: Option<String>,
+    // An empty string removes the secret
+    pub totp: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub urls: Option<Vec<String>>,
+    pub url_match: Option<UrlMatchMode>,
 }
 
 impl From<&Entry> for EntryPublic {
@@ -69,7 +189,78 @@ impl From<&Entry> for EntryPublic {
             url: entry.url.clone(),
             name: entry.name.clone(),
             favorite: entry.favorite,
-            password_strength: entry.password_strength.clone()
+            password_strength: entry.password_strength.clone(),
+            fields: entry.fields.iter().map(CustomFieldPublic::from).collect(),
+            has_totp: entry.totp.is_some(),
+            history_count: entry.password_history.len(),
+            deleted_at: entry.deleted_at,
+            tags: entry.tags.clone(),
+            folder_id: entry.folder_id,
+            urls: entry.urls.clone(),
+            url_match: entry.url_match,
+        }
+    }
+}
+
+impl Entry {
+    /// Replaces the password, moving the current one into the history.
+    pub fn set_password(&mut self, password: String, strength: PasswordStrength) {
+        if password == self.password {
+            self.password_strength = strength;
+            return;
+        }
+
+        let previous = PasswordHistoryEntry {
+            id: Uuid::new_v4(),
+            password: std::mem::replace(&mut self.password, password),
+            password_strength: std::mem::replace(&mut self.password_strength, strength),
+            replaced_at: OffsetDateTime::now_utc(),
+        };
+
+        // Restoring an old password shouldn't leave a duplicate of it behind
+        self.password_history.retain(|h| h.password != self.password);
+        self.password_history.insert(0, previous);
+        self.password_history.truncate(MAX_PASSWORD_HISTORY);
+    }
+
+    /// Makes a password from the history current again.
+    pub fn restore_password(&mut self, history_id: &Uuid) -> Option<()> {
+        let old = self.password_history.iter().find(|h| h.id == *history_id)?.clone();
+        self.set_password(old.password, old.password_strength);
+        Some(())
+    }
+
+    /// The main URL followed by the additional ones, skipping empty ones.
+    pub fn all_urls(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.url).chain(self.urls.iter()).filter(|u| !u.trim().is_empty())
+    }
+
+    /// Current and previous passwords, for reuse checks.
+    pub fn known_passwords(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.password).chain(self.password_history.iter().map(|h| &h.password))
+    }
+}
+
+impl From<&PasswordHistoryEntry> for PasswordHistoryInfo {
+    fn from(entry: &PasswordHistoryEntry) -> Self {
+        PasswordHistoryInfo {
+            id: entry.id,
+            password_strength: entry.password_strength.clone(),
+            replaced_at: entry.replaced_at,
+        }
+    }
+}
+
+impl From<&CustomField> for CustomFieldPublic {
+    fn from(field: &CustomField) -> Self {
+        CustomFieldPublic {
+            id: field.id,
+            name: field.name.clone(),
+            kind: field.kind.clone(),
+            value: match field.kind {
+                CustomFieldKind::Hidden => None,
+                _ => Some(field.value.clone()),
+            },
         }
     }
 }
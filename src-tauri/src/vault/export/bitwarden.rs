@@ -0,0 +1,75 @@
+use super::{export_error, ExportData};
+use crate::vault::entry::{CustomFieldKind, Entry, UrlMatchMode};
+use crate::vault::vault::{VaultErrorKind, VaultResult};
+use serde_json::{json, Value};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+const TYPE_LOGIN: u8 = 1;
+const FIELD_TEXT: u8 = 0;
+const FIELD_HIDDEN: u8 = 1;
+
+fn match_type(mode: UrlMatchMode) -> Option<u8> {
+    match mode {
+        // Bitwarden's default, left out like Bitwarden does
+        UrlMatchMode::BaseDomain => None,
+        UrlMatchMode::Host => Some(1),
+        UrlMatchMode::StartsWith => Some(2),
+        UrlMatchMode::RegEx => Some(4),
+        UrlMatchMode::Never => Some(5),
+    }
+}
+
+fn date(time: OffsetDateTime) -> Value {
+    time.format(&Rfc3339).map_or(Value::Null, Value::String)
+}
+
+fn item(entry: &Entry) -> Value {
+    let match_type = match_type(entry.url_match);
+
+    json!({
+        "id": entry.id,
+        "organizationId": null,
+        "folderId": entry.folder_id,
+        "type": TYPE_LOGIN,
+        "reprompt": 0,
+        "name": entry.label,
+        "notes": Some(&entry.notes).filter(|n| !n.is_empty()),
+        "favorite": entry.favorite,
+        "fields": entry.fields.iter().map(|f| json!({
+            "name": f.name,
+            "value": f.value,
+            "type": if f.kind == CustomFieldKind::Hidden { FIELD_HIDDEN } else { FIELD_TEXT },
+            "linkedId": null,
+        })).collect::<Vec<_>>(),
+        "login": {
+            "uris": entry.all_urls().map(|u| json!({ "match": match_type, "uri": u })).collect::<Vec<_>>(),
+            "username": Some(&entry.name).filter(|n| !n.is_empty()),
+            "password": entry.password,
+            "totp": entry.totp,
+        },
+        "passwordHistory": entry.password_history.iter().map(|h| json!({
+            "lastUsedDate": date(h.replaced_at),
+            "password": h.password,
+        })).collect::<Vec<_>>(),
+        "collectionIds": null,
+        "creationDate": date(entry.created_at),
+        "revisionDate": date(entry.modified_at),
+    })
+}
+
+/// Writes an unencrypted Bitwarden JSON export. Bitwarden nests folders by
+/// name, so every folder is written with its full path.
+pub fn write(data: &ExportData) -> VaultResult<Vec<u8>> {
+    let export = json!({
+        "encrypted": false,
+        "folders": data.folders.iter().map(|f| json!({
+            "id": f.id,
+            "name": data.folder_path(Some(f.id)).join("/"),
+        })).collect::<Vec<_>>(),
+        "items": data.entries.iter().map(|e| item(e)).collect::<Vec<_>>(),
+    });
+
+    serde_json::to_vec_pretty(&export)
+        .map_err(|e| export_error(VaultErrorKind::Parse, format!("Couldn't write Bitwarden export: {}", e)))
+}
@@ -0,0 +1,32 @@
+use super::{export_error, ExportData};
+use crate::vault::vault::{VaultErrorKind, VaultResult};
+
+// Chrome's columns first, so browsers can import the file as well
+const HEADER: [&str; 8] = ["name", "url", "username", "password", "note", "totp", "folder", "favorite"];
+
+/// Writes one row per login. Custom fields, tags and additional URLs have no column.
+pub fn write(data: &ExportData) -> VaultResult<Vec<u8>> {
+    let error = |e: ::csv::Error| export_error(VaultErrorKind::Parse, format!("Couldn't write CSV: {}", e));
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+
+    writer.write_record(HEADER).map_err(error)?;
+
+    for entry in &data.entries {
+        writer
+            .write_record([
+                entry.label.as_str(),
+                entry.url.as_str(),
+                entry.name.as_str(),
+                entry.password.as_str(),
+                entry.notes.as_str(),
+                entry.totp.as_deref().unwrap_or_default(),
+                &data.folder_path(entry.folder_id).join("/"),
+                if entry.favorite { "1" } else { "" },
+            ])
+            .map_err(error)?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| export_error(VaultErrorKind::Parse, format!("Couldn't write CSV: {}", e.error())))
+}
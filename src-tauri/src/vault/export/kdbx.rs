@@ -0,0 +1,182 @@
+use super::{keepass, ExportData};
+use crate::vault::crypto::KdfParams;
+use crate::vault::kdbx;
+use crate::vault::vault::VaultResult;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// Writes a KDBX 4 database holding the same groups and entries as the XML export.
+pub fn write(data: &ExportData, password: Option<&str>, key_file: Option<&Path>, kdf: &KdfParams) -> VaultResult<Vec<u8>> {
+    let mut key = kdbx::composite_key(password, key_file)?;
+    let sealed = kdbx::seal(&key, kdf, |stream| keepass::write(data, Some(stream)));
+    key.zeroize();
+
+    sealed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::entry::{CustomField, CustomFieldKind, Entry, PasswordHistoryEntry};
+    use crate::vault::folder::Folder;
+    use crate::vault::import::{keepass as reader, ImportedEntry};
+    use crate::vault::vault::VaultErrorKind;
+    use std::fs;
+    use tempfile::TempDir;
+    use time::format_description::well_known::Rfc3339;
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    const PASSWORD: &str = "export-password";
+
+    fn date(value: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(value, &Rfc3339).unwrap()
+    }
+
+    fn cheap_kdf() -> KdfParams {
+        KdfParams {
+            m_cost: 1024,
+            t_cost: 1,
+            p_cost: 1,
+            ..Default::default()
+        }
+    }
+
+    fn history(password: &str, replaced_at: &str) -> PasswordHistoryEntry {
+        PasswordHistoryEntry {
+            id: Uuid::new_v4(),
+            password: password.into(),
+            password_strength: Default::default(),
+            replaced_at: date(replaced_at),
+        }
+    }
+
+    fn field(name: &str, value: &str, kind: CustomFieldKind) -> CustomField {
+        CustomField {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            kind,
+            value: value.into(),
+        }
+    }
+
+    fn login(label: &str, password: &str, folder_id: Option<Uuid>) -> Entry {
+        Entry {
+            id: Uuid::new_v4(),
+            created_at: date("2020-01-01T00:00:00Z"),
+            modified_at: date("2024-05-01T00:00:00Z"),
+            last_used: None,
+            uses: 0,
+            label: label.into(),
+            url: format!("https://{}.example.com", label.to_lowercase()),
+            name: "me@example.com".into(),
+            password: password.into(),
+            password_strength: Default::default(),
+            favorite: false,
+            fields: Vec::new(),
+            notes: String::new(),
+            totp: None,
+            password_history: Vec::new(),
+            deleted_at: None,
+            tags: Vec::new(),
+            folder_id,
+            urls: Vec::new(),
+            url_match: Default::default(),
+        }
+    }
+
+    /// Writes `entries` as a KDBX file and reads it back like an import does.
+    fn round_trip(entries: &[Entry], folders: &[Folder], password: Option<&str>, key_file: Option<&Path>) -> Vec<ImportedEntry> {
+        let data = ExportData {
+            entries: entries.iter().collect(),
+            folders,
+        };
+        let file = write(&data, password, key_file, &cheap_kdf()).unwrap();
+
+        let key = kdbx::composite_key(password, key_file).unwrap();
+        let (xml, mut stream) = kdbx::open(&file, &key).unwrap();
+        reader::read(&xml, Some(&mut stream)).unwrap().entries
+    }
+
+    #[test]
+    fn round_trip_keeps_secrets_history_and_folders() {
+        let work = Folder {
+            id: Uuid::new_v4(),
+            name: "Work".into(),
+            parent_id: None,
+        };
+        let servers = Folder {
+            id: Uuid::new_v4(),
+            name: "Servers".into(),
+            parent_id: Some(work.id),
+        };
+
+        let mut mail = login("Mail", "current-pässword", None);
+        mail.urls = vec!["https://webmail.example.com".into()];
+        mail.totp = Some("jbsw y3dp ehpk 3pxp".into());
+        mail.fields = vec![
+            field("PIN", "4321", CustomFieldKind::Hidden),
+            field("Security question", "First pet?", CustomFieldKind::Text),
+        ];
+        // Newest first, like on entries
+        mail.password_history = vec![
+            history("second-password", "2024-05-01T00:00:00Z"),
+            history("first-password", "2022-06-01T00:00:00Z"),
+        ];
+
+        let mut server = login("Server", "server-password", Some(servers.id));
+        server.password_history = vec![history("old-server-password", "2023-01-01T00:00:00Z")];
+
+        let read = round_trip(&[mail, server], &[work, servers], Some(PASSWORD), None);
+        assert_eq!(read.len(), 2);
+
+        let mail = &read[0];
+        assert_eq!(mail.label, "Mail");
+        assert_eq!(mail.password, "current-pässword");
+        assert!(mail.folder.is_empty());
+        assert_eq!(mail.urls, ["https://webmail.example.com"]);
+        assert_eq!(mail.totp.as_deref(), Some("otpauth://totp/Mail?secret=JBSWY3DPEHPK3PXP"));
+
+        let fields: Vec<(&str, &str, bool)> = mail
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.value.as_str(), f.kind == CustomFieldKind::Hidden))
+            .collect();
+        assert_eq!(fields, [("PIN", "4321", true), ("Security question", "First pet?", false)]);
+
+        let mut history: Vec<_> = mail.password_history.iter().map(|h| (h.password.as_str(), h.replaced_at)).collect();
+        history.sort_by_key(|(_, replaced_at)| std::cmp::Reverse(*replaced_at));
+        assert_eq!(
+            history,
+            [
+                ("second-password", date("2024-05-01T00:00:00Z")),
+                ("first-password", date("2022-06-01T00:00:00Z")),
+            ]
+        );
+
+        let server = &read[1];
+        assert_eq!(server.folder, ["Work", "Servers"]);
+        assert_eq!(server.password, "server-password");
+        let history: Vec<&str> = server.password_history.iter().map(|h| h.password.as_str()).collect();
+        assert_eq!(history, ["old-server-password"]);
+    }
+
+    #[test]
+    fn round_trip_with_key_file_only() {
+        let dir = TempDir::new().unwrap();
+        let key_file = dir.path().join("export.key");
+        fs::write(&key_file, b"any file can be a key file").unwrap();
+
+        let read = round_trip(&[login("Mail", "mail-password", None)], &[], None, Some(&key_file));
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].password, "mail-password");
+
+        let data = ExportData {
+            entries: Vec::new(),
+            folders: &[],
+        };
+        let file = write(&data, None, Some(&key_file), &cheap_kdf()).unwrap();
+        let error = kdbx::open(&file, &kdbx::composite_key(Some(PASSWORD), None).unwrap()).err().unwrap();
+        assert!(matches!(error.kind, VaultErrorKind::Auth));
+    }
+}
@@ -0,0 +1,209 @@
+use super::ExportData;
+use crate::vault::entry::{CustomFieldKind, Entry};
+use crate::vault::kdbx::{self, InnerStream};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::collections::HashSet;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use url::form_urlencoded;
+use uuid::Uuid;
+
+// Keys KeePass gives a place of its own, custom fields can't reuse them
+const RESERVED_KEYS: [&str; 6] = ["Title", "UserName", "Password", "URL", "Notes", "otp"];
+
+/// Escapes text for element content and attribute values. Control characters
+/// XML 1.0 can't hold are dropped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn uuid(id: Uuid) -> String {
+    STANDARD.encode(id.as_bytes())
+}
+
+/// KeePassXC reads an `otpauth://` URI from the `otp` string, bare secrets are wrapped in one.
+fn otp_uri(label: &str, totp: &str) -> String {
+    if totp.trim().to_ascii_lowercase().starts_with("otpauth://") {
+        return totp.trim().to_string();
+    }
+
+    let secret: String = totp.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+    let label: String = form_urlencoded::byte_serialize(label.as_bytes()).collect();
+
+    format!("otpauth://totp/{}?secret={}", label, secret)
+}
+
+/// Adds " (2)", " (3)" and so on until `key` is free.
+fn unique_key(key: &str, used: &mut HashSet<String>) -> String {
+    let key = if key.trim().is_empty() { "Field" } else { key.trim() };
+    let mut candidate = key.to_string();
+    let mut n = 1;
+
+    while !used.insert(candidate.clone()) {
+        n += 1;
+        candidate = format!("{} ({})", key, n);
+    }
+
+    candidate
+}
+
+/// Writes the document. KDBX files need `stream` to encrypt protected values
+/// and store times in their binary form.
+struct XmlWriter<'a> {
+    out: String,
+    depth: usize,
+    stream: Option<&'a mut InnerStream>,
+}
+
+impl XmlWriter<'_> {
+    fn line(&mut self, content: &str) {
+        self.out.push_str(&"\t".repeat(self.depth));
+        self.out.push_str(content);
+        self.out.push('\n');
+    }
+
+    fn open(&mut self, tag: &str) {
+        self.line(&format!("<{}>", tag));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", tag));
+    }
+
+    fn element(&mut self, tag: &str, text: &str) {
+        self.line(&format!("<{0}>{1}</{0}>", tag, escape(text)));
+    }
+
+    fn time(&mut self, tag: &str, time: OffsetDateTime) {
+        let value = match self.stream {
+            Some(_) => kdbx::encode_time(time),
+            None => time.replace_nanosecond(0).unwrap_or(time).format(&Rfc3339).unwrap_or_default(),
+        };
+        self.element(tag, &value);
+    }
+
+    fn string(&mut self, key: &str, value: &str, protected: bool) {
+        let (attribute, value) = match self.stream.as_deref_mut() {
+            Some(stream) if protected => (" Protected=\"True\"", stream.encrypt(value)),
+            None if protected => (" ProtectInMemory=\"True\"", escape(value)),
+            _ => ("", escape(value)),
+        };
+
+        self.line(&format!("<String><Key>{}</Key><Value{}>{}</Value></String>", escape(key), attribute, value));
+    }
+
+    /// Writes an entry, `password` stands in for the current one in history snapshots.
+    fn entry(&mut self, entry: &Entry, password: &str, modified_at: OffsetDateTime, history: bool) {
+        self.open("Entry");
+        self.element("UUID", &uuid(entry.id));
+        self.element("Tags", &entry.tags.join(";"));
+
+        self.open("Times");
+        self.time("CreationTime", entry.created_at);
+        self.time("LastModificationTime", modified_at);
+        self.time("LastAccessTime", entry.last_used.unwrap_or(modified_at));
+        self.time("ExpiryTime", modified_at);
+        self.element("Expires", "False");
+        self.element("UsageCount", &entry.uses.to_string());
+        self.time("LocationChanged", modified_at);
+        self.close("Times");
+
+        self.string("Title", &entry.label, false);
+        self.string("UserName", &entry.name, false);
+        self.string("Password", password, true);
+        self.string("URL", &entry.url, false);
+        self.string("Notes", &entry.notes, false);
+
+        if let Some(totp) = entry.totp.as_deref().filter(|t| !t.trim().is_empty()) {
+            self.string("otp", &otp_uri(&entry.label, totp), true);
+        }
+
+        let mut used: HashSet<String> = RESERVED_KEYS.iter().map(|k| k.to_string()).collect();
+
+        // KeePassXC and Keepass2Android read additional URLs from these
+        for (i, url) in entry.urls.iter().filter(|u| !u.is_empty()).enumerate() {
+            let key = if i == 0 { "KP2A_URL".to_string() } else { format!("KP2A_URL_{}", i) };
+            self.string(&unique_key(&key, &mut used), url, false);
+        }
+
+        for field in &entry.fields {
+            self.string(&unique_key(&field.name, &mut used), &field.value, field.kind == CustomFieldKind::Hidden);
+        }
+
+        // KeePass keeps the oldest version first, dated to when it was saved
+        if history && !entry.password_history.is_empty() {
+            self.open("History");
+            for (i, old) in entry.password_history.iter().enumerate().rev() {
+                let set_at = entry.password_history.get(i + 1).map_or(entry.created_at, |h| h.replaced_at);
+                self.entry(entry, &old.password, set_at, false);
+            }
+            self.close("History");
+        }
+
+        self.close("Entry");
+    }
+
+    fn group(&mut self, data: &ExportData, id: Option<Uuid>, name: &str) {
+        self.open("Group");
+        self.element("UUID", &uuid(id.unwrap_or_else(Uuid::new_v4)));
+        self.element("Name", name);
+
+        for entry in data.entries.iter().filter(|e| e.folder_id == id) {
+            self.entry(entry, &entry.password, entry.modified_at, true);
+        }
+
+        for folder in data.folders.iter().filter(|f| f.parent_id == id) {
+            self.group(data, Some(folder.id), &folder.name);
+        }
+
+        self.close("Group");
+    }
+}
+
+/// Writes the KeePass 2 XML format, or the XML inside a KDBX file when
+/// `stream` is set. Folders become groups under a root group, previous
+/// passwords become history entries.
+pub fn write(data: &ExportData, stream: Option<&mut InnerStream>) -> String {
+    let mut writer = XmlWriter {
+        out: String::from("<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n"),
+        depth: 0,
+        stream,
+    };
+
+    writer.open("KeePassFile");
+    writer.open("Meta");
+    writer.element("Generator", "Nebula");
+    writer.element("DatabaseName", "Nebula");
+    writer.open("MemoryProtection");
+    writer.element("ProtectTitle", "False");
+    writer.element("ProtectUserName", "False");
+    writer.element("ProtectPassword", "True");
+    writer.element("ProtectURL", "False");
+    writer.element("ProtectNotes", "False");
+    writer.close("MemoryProtection");
+    writer.element("RecycleBinEnabled", "False");
+    writer.close("Meta");
+
+    writer.open("Root");
+    writer.group(data, None, "Nebula");
+    writer.close("Root");
+    writer.close("KeePassFile");
+
+    writer.out
+}
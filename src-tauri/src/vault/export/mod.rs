@@ -0,0 +1,86 @@
+pub mod bitwarden;
+pub mod csv;
+pub mod kdbx;
+pub mod keepass;
+pub mod nebula;
+
+use crate::vault::crypto::KdfParams;
+use crate::vault::entry::Entry;
+use crate::vault::folder::Folder;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use serde::Deserialize;
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum ExportFormat {
+    // Columns read by Chrome, Firefox and our own importer
+    Csv,
+    // Unencrypted JSON export
+    Bitwarden,
+    KeePassXml,
+    // KeePass 2 database, locked with the export password and/or a key file
+    Kdbx,
+    // Encrypted with a password of its own
+    Nebula,
+}
+
+/// Logins and the folder tree they sit in, as handed to the exporters.
+pub struct ExportData<'a> {
+    pub entries: Vec<&'a Entry>,
+    pub folders: &'a [Folder],
+}
+
+impl ExportData<'_> {
+    /// Names of the folders from the root down to `id`.
+    pub fn folder_path(&self, id: Option<Uuid>) -> Vec<&str> {
+        let mut path = Vec::new();
+        let mut current = id;
+
+        // Bounded by the folder count in case the tree has a cycle
+        while let Some(folder) = current.and_then(|id| self.folders.iter().find(|f| f.id == id)) {
+            if path.len() == self.folders.len() {
+                break;
+            }
+
+            path.push(folder.name.as_str());
+            current = folder.parent_id;
+        }
+
+        path.reverse();
+        path
+    }
+}
+
+pub fn export_error(kind: VaultErrorKind, message: impl Into<String>) -> VaultError {
+    VaultError {
+        kind,
+        severity: VaultErrorSeverity::Soft,
+        message: message.into(),
+        code: "E_EXPORT",
+    }
+}
+
+/// Serializes the export. `password` is only used by the encrypted formats, which
+/// need it unless a KeePass database is locked with just `key_file`.
+pub fn render(
+    format: ExportFormat,
+    data: &ExportData,
+    password: Option<&str>,
+    key_file: Option<&Path>,
+    kdf: &KdfParams,
+) -> VaultResult<Vec<u8>> {
+    match format {
+        ExportFormat::Csv => csv::write(data),
+        ExportFormat::Bitwarden => bitwarden::write(data),
+        ExportFormat::KeePassXml => Ok(keepass::write(data, None).into_bytes()),
+        ExportFormat::Kdbx => kdbx::write(data, password, key_file, kdf),
+        ExportFormat::Nebula => {
+            let password = password
+                .filter(|p| !p.is_empty())
+                .ok_or_else(|| export_error(VaultErrorKind::Auth, "An export password is required"))?;
+
+            nebula::seal(data, password, kdf)
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use super::{export_error, ExportData};
+use crate::vault::crypto::{check_argon2_costs, KdfParams, VaultCryptoManager};
+use crate::vault::entry::Entry;
+use crate::vault::folder::Folder;
+use crate::vault::vault::{VaultErrorKind, VaultResult};
+use argon2::password_hash::rand_core::RngCore;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, OsRng, Payload},
+    AeadCore, Key, KeyInit, XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+const FORMAT: &str = "nebula-export";
+const VERSION: u32 = 1;
+
+/// Export file, readable without the vault it came from.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NebulaExport {
+    format: String,
+    version: u32,
+    kdf: KdfParams,
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NebulaPayload {
+    pub entries: Vec<Entry>,
+    pub folders: Vec<Folder>,
+}
+
+/// Binds the header to the ciphertext, like the vault file does.
+fn aad(export: &NebulaExport) -> Vec<u8> {
+    let kdf = &export.kdf;
+    format!(
+        "{};version={};salt={};kdf={},{},{},{},{}",
+        FORMAT, export.version, export.salt, kdf.algorithm, kdf.version, kdf.m_cost, kdf.t_cost, kdf.p_cost
+    )
+    .into_bytes()
+}
+
+fn derive_key(password: &str, salt: &[u8], kdf: &KdfParams) -> VaultResult<[u8; 32]> {
+    VaultCryptoManager::derive_kek(password.as_bytes(), salt, kdf)
+        .map_err(|e| export_error(VaultErrorKind::Crypto, format!("Failed to derive export key: {}", e)))
+}
+
+/// Encrypts the logins and folders with a key derived from `password` and a
+/// fresh salt, using the vault's key derivation settings.
+pub fn seal(data: &ExportData, password: &str, kdf: &KdfParams) -> VaultResult<Vec<u8>> {
+    let mut salt = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut export = NebulaExport {
+        format: FORMAT.into(),
+        version: VERSION,
+        kdf: kdf.clone(),
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        data: String::new(),
+    };
+
+    let payload = NebulaPayload {
+        entries: data.entries.iter().map(|e| (*e).clone()).collect(),
+        folders: data.folders.to_vec(),
+    };
+    let mut plain = serde_json::to_vec(&payload)
+        .map_err(|e| export_error(VaultErrorKind::Parse, format!("Serialization failed: {}", e)))?;
+
+    let mut key = derive_key(password, &salt, kdf)?;
+    let encrypted = XChaCha20Poly1305::new(Key::from_slice(&key)).encrypt(&nonce, Payload { msg: &plain, aad: &aad(&export) });
+    key.zeroize();
+    plain.zeroize();
+
+    export.data = STANDARD.encode(
+        encrypted.map_err(|e| export_error(VaultErrorKind::Crypto, format!("Failed to encrypt export: {}", e)))?,
+    );
+
+    serde_json::to_vec_pretty(&export)
+        .map_err(|e| export_error(VaultErrorKind::Parse, format!("Serialization failed: {}", e)))
+}
+
+/// Decrypts an export written by `seal`. A wrong password fails with an Auth error.
+pub fn open(text: &str, password: &str) -> VaultResult<NebulaPayload> {
+    let invalid = || export_error(VaultErrorKind::Parse, "Not a Nebula export");
+
+    let export: NebulaExport = serde_json::from_str(text).map_err(|_| invalid())?;
+    if export.format != FORMAT {
+        return Err(invalid());
+    }
+    if export.version > VERSION {
+        return Err(export_error(VaultErrorKind::Parse, "Export was written by a newer version of Nebula"));
+    }
+
+    let decode = |value: &str| STANDARD.decode(value).map_err(|_| invalid());
+    let salt = decode(&export.salt)?;
+    let nonce = decode(&export.nonce)?;
+    let data = decode(&export.data)?;
+
+    if nonce.len() != 24 {
+        return Err(invalid());
+    }
+
+    check_argon2_costs(export.kdf.m_cost, export.kdf.t_cost, export.kdf.p_cost)
+        .map_err(|e| export_error(VaultErrorKind::Parse, format!("Invalid export KDF parameters: {}", e)))?;
+
+    let mut key = derive_key(password, &salt, &export.kdf)?;
+    let decrypted = XChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(XNonce::from_slice(&nonce), Payload { msg: &data, aad: &aad(&export) });
+    key.zeroize();
+
+    let mut plain = decrypted.map_err(|_| export_error(VaultErrorKind::Auth, "Wrong export password"))?;
+    let payload = serde_json::from_slice(&plain).map_err(|_| invalid());
+    plain.zeroize();
+
+    payload
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Node of the folder tree. Folders without a parent sit at the root.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Folder {
+    pub id: Uuid,
+    pub name: String,
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryFilter {
+    pub folder_id: Option<Uuid>,
+    #[serde(default)]
+    pub include_subfolders: bool,
+    // Only entries that aren't in any folder, ignored when `folder_id` is set
+    #[serde(default)]
+    pub unfiled: bool,
+    pub tag: Option<String>,
+}
+
+/// `id` and every folder below it.
+pub fn subtree(folders: &[Folder], id: Uuid) -> Vec<Uuid> {
+    let mut ids = vec![id];
+    let mut i = 0;
+
+    while i < ids.len() {
+        let parent = ids[i];
+        ids.extend(folders.iter().filter(|f| f.parent_id == Some(parent)).map(|f| f.id));
+        i += 1;
+    }
+
+    ids
+}
+
+/// Trims tags and drops empty ones and case-insensitive duplicates.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !normalized.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
+        }
+    }
+
+    normalized
+}
@@ -0,0 +1,283 @@
+use crate::vault::storage;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use argon2::password_hash::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const PRESETS_FILE: &str = "generator.json";
+
+// BIP-39 English list, 2048 words so every word adds 11 bits
+pub const WORDLIST: &str = include_str!("../../data/wordlist.txt");
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()_+-=[]{}|;:,.<>?";
+// Characters that are easy to mistake for each other when read or typed
+const AMBIGUOUS: &str = "Il1O0o|";
+
+pub const MIN_LENGTH: usize = 4;
+pub const MAX_LENGTH: usize = 256;
+pub const MIN_WORDS: usize = 3;
+pub const MAX_WORDS: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PasswordOptions {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    // Only apply to enabled classes
+    pub min_lowercase: usize,
+    pub min_uppercase: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+    pub exclude_ambiguous: bool,
+    // Further characters to leave out
+    pub exclude: String,
+}
+
+impl Default for PasswordOptions {
+    fn default() -> Self {
+        PasswordOptions {
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            min_lowercase: 1,
+            min_uppercase: 1,
+            min_digits: 1,
+            min_symbols: 1,
+            exclude_ambiguous: false,
+            exclude: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    // Appends a digit to one of the words
+    pub include_number: bool,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        PassphraseOptions {
+            words: 6,
+            separator: String::from("-"),
+            capitalize: false,
+            include_number: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum GeneratorOptions {
+    Password(PasswordOptions),
+    Passphrase(PassphraseOptions),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratorPreset {
+    pub id: Uuid,
+    pub name: String,
+    pub options: GeneratorOptions,
+}
+
+fn invalid(message: String) -> VaultError {
+    VaultError {
+        kind: VaultErrorKind::Validation,
+        severity: VaultErrorSeverity::Soft,
+        message,
+        code: "E_GENERATOR_OPTIONS",
+    }
+}
+
+/// Uniform index below `bound`, rejecting the values that would bias a plain modulo.
+fn random_index(bound: usize) -> usize {
+    let bound = bound as u32;
+    let zone = u32::MAX - u32::MAX % bound;
+
+    loop {
+        let value = OsRng.next_u32();
+        if value < zone {
+            return (value % bound) as usize;
+        }
+    }
+}
+
+fn pick<T: Copy>(pool: &[T]) -> T {
+    pool[random_index(pool.len())]
+}
+
+fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, random_index(i + 1));
+    }
+}
+
+fn generate_password(options: &PasswordOptions) -> VaultResult<String> {
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&options.length) {
+        return Err(invalid(format!("Length must be between {} and {}", MIN_LENGTH, MAX_LENGTH)));
+    }
+
+    let excluded = |c: &char| options.exclude.contains(*c) || (options.exclude_ambiguous && AMBIGUOUS.contains(*c));
+
+    let classes: Vec<(Vec<char>, usize)> = [
+        (options.lowercase, LOWERCASE, options.min_lowercase),
+        (options.uppercase, UPPERCASE, options.min_uppercase),
+        (options.digits, DIGITS, options.min_digits),
+        (options.symbols, SYMBOLS, options.min_symbols),
+    ]
+    .into_iter()
+    .filter(|(enabled, _, _)| *enabled)
+    .map(|(_, chars, min)| (chars.chars().filter(|c| !excluded(c)).collect(), min))
+    .collect();
+
+    if classes.iter().any(|(pool, min)| pool.is_empty() && *min > 0) {
+        return Err(invalid("Exclusions leave no characters for a required class".into()));
+    }
+
+    let all: Vec<char> = classes.iter().flat_map(|(pool, _)| pool.iter().copied()).collect();
+    if all.is_empty() {
+        return Err(invalid("At least one character class has to be enabled".into()));
+    }
+
+    if classes.iter().map(|(_, min)| min).sum::<usize>() > options.length {
+        return Err(invalid("Minimum character counts exceed the length".into()));
+    }
+
+    // Minimums first, then the rest from every class, shuffled so the
+    // required characters don't sit at the start
+    let mut password: Vec<char> = classes
+        .iter()
+        .flat_map(|(pool, min)| (0..*min).map(|_| pick(pool)))
+        .collect();
+
+    while password.len() < options.length {
+        password.push(pick(&all));
+    }
+
+    shuffle(&mut password);
+
+    Ok(password.into_iter().collect())
+}
+
+fn generate_passphrase(options: &PassphraseOptions) -> VaultResult<String> {
+    if !(MIN_WORDS..=MAX_WORDS).contains(&options.words) {
+        return Err(invalid(format!("Word count must be between {} and {}", MIN_WORDS, MAX_WORDS)));
+    }
+
+    let wordlist: Vec<&str> = WORDLIST.lines().map(str::trim).filter(|w| !w.is_empty()).collect();
+
+    let mut words: Vec<String> = (0..options.words)
+        .map(|_| {
+            let word = pick(&wordlist);
+            if options.capitalize {
+                word[..1].to_uppercase() + &word[1..]
+            } else {
+                word.to_string()
+            }
+        })
+        .collect();
+
+    if options.include_number {
+        let digits: Vec<char> = DIGITS.chars().collect();
+        let i = random_index(words.len());
+        words[i].push(pick(&digits));
+    }
+
+    Ok(words.join(&options.separator))
+}
+
+pub fn generate(options: &GeneratorOptions) -> VaultResult<String> {
+    match options {
+        GeneratorOptions::Password(options) => generate_password(options),
+        GeneratorOptions::Passphrase(options) => generate_passphrase(options),
+    }
+}
+
+/// Saved generator settings, kept next to the vault file.
+#[derive(Default)]
+pub struct Generator {
+    path: PathBuf,
+    presets: Vec<GeneratorPreset>,
+}
+
+impl Generator {
+    pub fn set_vault_path(&mut self, vault_path: &Path) {
+        self.path = vault_path.with_file_name(PRESETS_FILE);
+
+        self.presets = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn get_presets(&self) -> Vec<GeneratorPreset> {
+        self.presets.clone()
+    }
+
+    pub fn get_preset(&self, id: &Uuid) -> Option<&GeneratorPreset> {
+        self.presets.iter().find(|p| p.id == *id)
+    }
+
+    /// Adds the preset, or replaces the one with the same id.
+    pub fn save_preset(&mut self, preset: GeneratorPreset) -> VaultResult<()> {
+        // Rejects options that could never generate anything
+        generate(&preset.options)?;
+
+        let mut presets = self.presets.clone();
+        match presets.iter_mut().find(|p| p.id == preset.id) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+
+        self.write(presets)
+    }
+
+    pub fn delete_preset(&mut self, id: &Uuid) -> VaultResult<()> {
+        if self.get_preset(id).is_none() {
+            return Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Preset '{}' not found", id),
+                code: "E_GENERATOR_PRESETS",
+            });
+        }
+
+        let presets = self.presets.iter().filter(|p| p.id != *id).cloned().collect();
+        self.write(presets)
+    }
+
+    fn write(&mut self, presets: Vec<GeneratorPreset>) -> VaultResult<()> {
+        let content = serde_json::to_string_pretty(&presets).map_err(|e| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Serialization failed: {e}"),
+            code: "E_GENERATOR_PRESETS",
+        })?;
+
+        storage::write_atomic(&self.path, content.as_bytes()).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Couldn't save generator presets: {e}"),
+            code: "E_GENERATOR_PRESETS",
+        })?;
+
+        self.presets = presets;
+
+        Ok(())
+    }
+}
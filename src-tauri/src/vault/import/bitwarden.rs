@@ -0,0 +1,291 @@
+use super::{import_error, ImportedEntry, ParsedImport};
+use crate::vault::crypto::check_argon2_costs;
+use crate::vault::entry::UrlMatchMode;
+use crate::vault::vault::{VaultErrorKind, VaultResult};
+use aes::Aes256;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use zeroize::Zeroize;
+
+const TYPE_LOGIN: u8 = 1;
+const FIELD_HIDDEN: u8 = 1;
+const FIELD_LINKED: u8 = 3;
+const KDF_PBKDF2: u8 = 0;
+const KDF_ARGON2: u8 = 1;
+// Bitwarden allows up to 2 million, anything far beyond that isn't a real export
+const PBKDF2_MAX_ITERATIONS: u32 = 10_000_000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    password_protected: bool,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    #[serde(default)]
+    items: Vec<Item>,
+
+    // Only set on password protected exports
+    salt: Option<String>,
+    kdf_type: Option<u8>,
+    kdf_iterations: Option<u32>,
+    kdf_memory: Option<u32>,
+    kdf_parallelism: Option<u32>,
+    #[serde(rename = "encKeyValidation_DO_NOT_EDIT")]
+    key_validation: Option<String>,
+    data: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    #[serde(default)]
+    name: String,
+    notes: Option<String>,
+    #[serde(default)]
+    favorite: bool,
+    folder_id: Option<String>,
+    #[serde(default)]
+    fields: Vec<Field>,
+    login: Option<Login>,
+    password_history: Option<Vec<PasswordHistory>>,
+    creation_date: Option<String>,
+    revision_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Field {
+    name: Option<String>,
+    value: Option<String>,
+    #[serde(rename = "type")]
+    kind: u8,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    #[serde(default)]
+    uris: Vec<Uri>,
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PasswordHistory {
+    last_used_date: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    #[serde(rename = "match")]
+    match_type: Option<u8>,
+    uri: Option<String>,
+}
+
+struct StretchedKey {
+    enc: [u8; 32],
+    mac: [u8; 32],
+}
+
+impl Drop for StretchedKey {
+    fn drop(&mut self) {
+        self.enc.zeroize();
+        self.mac.zeroize();
+    }
+}
+
+fn url_match(match_type: Option<u8>) -> UrlMatchMode {
+    match match_type {
+        Some(1) => UrlMatchMode::Host,
+        // Exact matches are closest to a prefix match of the whole URL
+        Some(2) | Some(3) => UrlMatchMode::StartsWith,
+        Some(4) => UrlMatchMode::RegEx,
+        Some(5) => UrlMatchMode::Never,
+        _ => UrlMatchMode::BaseDomain,
+    }
+}
+
+fn parse_date(date: &Option<String>) -> Option<OffsetDateTime> {
+    OffsetDateTime::parse(date.as_deref()?, &Rfc3339).ok()
+}
+
+/// Derives the export key like Bitwarden does for PINs and export passwords,
+/// then stretches it into separate encryption and MAC keys.
+fn derive_key(export: &Export, password: &str) -> VaultResult<StretchedKey> {
+    let salt = export.salt.as_deref().ok_or_else(|| import_error(VaultErrorKind::Parse, "Export salt is missing"))?;
+    let iterations = export.kdf_iterations.unwrap_or(600_000);
+    let invalid = |e: String| import_error(VaultErrorKind::Parse, format!("Invalid export KDF parameters: {}", e));
+    let mut key = [0u8; 32];
+
+    match export.kdf_type.unwrap_or(KDF_PBKDF2) {
+        KDF_PBKDF2 => {
+            if !(1..=PBKDF2_MAX_ITERATIONS).contains(&iterations) {
+                return Err(invalid(format!("{} PBKDF2 iterations", iterations)));
+            }
+
+            pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), iterations, &mut key)
+        }
+        KDF_ARGON2 => {
+            // Memory is given in MiB
+            let memory = export
+                .kdf_memory
+                .unwrap_or(64)
+                .checked_mul(1024)
+                .ok_or_else(|| invalid("memory is out of range".into()))?;
+            let parallelism = export.kdf_parallelism.unwrap_or(4);
+
+            check_argon2_costs(memory, iterations, parallelism).map_err(invalid)?;
+            let params = Params::new(memory, iterations, parallelism, Some(32)).map_err(|e| invalid(e.to_string()))?;
+
+            // Argon2 gets the hashed salt
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(password.as_bytes(), &Sha256::digest(salt.as_bytes()), &mut key)
+                .map_err(|e| import_error(VaultErrorKind::Crypto, format!("Key derivation failed: {}", e)))?;
+        }
+        other => return Err(import_error(VaultErrorKind::Parse, format!("Unsupported export KDF type {}", other))),
+    }
+
+    let hkdf = Hkdf::<Sha256>::from_prk(&key).map_err(|_| import_error(VaultErrorKind::Crypto, "Invalid export key"))?;
+    key.zeroize();
+
+    let mut stretched = StretchedKey { enc: [0; 32], mac: [0; 32] };
+    hkdf.expand(b"enc", &mut stretched.enc)
+        .and_then(|_| hkdf.expand(b"mac", &mut stretched.mac))
+        .map_err(|_| import_error(VaultErrorKind::Crypto, "Couldn't stretch the export key"))?;
+
+    Ok(stretched)
+}
+
+/// Decrypts an `2.iv|data|mac` string, AES-256-CBC with HMAC-SHA256.
+fn decrypt(value: &str, key: &StretchedKey) -> VaultResult<String> {
+    let invalid = || import_error(VaultErrorKind::Parse, "Encrypted export data is malformed");
+
+    let (kind, rest) = value.split_once('.').ok_or_else(invalid)?;
+    if kind != "2" {
+        return Err(import_error(VaultErrorKind::Parse, format!("Unsupported encryption type {}", kind)));
+    }
+
+    let parts = rest
+        .split('|')
+        .map(|p| STANDARD.decode(p).map_err(|_| invalid()))
+        .collect::<VaultResult<Vec<Vec<u8>>>>()?;
+    let [iv, data, mac] = parts.as_slice() else {
+        return Err(invalid());
+    };
+
+    let mut hmac = <Hmac<Sha256> as Mac>::new_from_slice(&key.mac).expect("HMAC accepts any key length");
+    hmac.update(iv);
+    hmac.update(data);
+    hmac.verify_slice(mac)
+        .map_err(|_| import_error(VaultErrorKind::Auth, "Wrong export password"))?;
+
+    let plain = cbc::Decryptor::<Aes256>::new_from_slices(&key.enc, iv)
+        .map_err(|_| invalid())?
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map_err(|_| invalid())?;
+
+    String::from_utf8(plain).map_err(|_| invalid())
+}
+
+fn decrypt_export(export: &Export, password: Option<&str>) -> VaultResult<Export> {
+    if !export.password_protected {
+        return Err(import_error(
+            VaultErrorKind::Access,
+            "Account restricted exports can only be imported into Bitwarden, export with a password instead",
+        ));
+    }
+
+    let password = password.ok_or_else(|| import_error(VaultErrorKind::Auth, "This export is protected by a password"))?;
+    let key = derive_key(export, password)?;
+
+    if let Some(validation) = &export.key_validation {
+        decrypt(validation, &key)?;
+    }
+
+    let data = export.data.as_deref().ok_or_else(|| import_error(VaultErrorKind::Parse, "Encrypted export has no data"))?;
+
+    serde_json::from_str(&decrypt(data, &key)?)
+        .map_err(|e| import_error(VaultErrorKind::Parse, format!("Decrypted export is not valid: {}", e)))
+}
+
+pub fn parse(text: &str, password: Option<&str>) -> VaultResult<ParsedImport> {
+    let mut export: Export = serde_json::from_str(text)
+        .map_err(|e| import_error(VaultErrorKind::Parse, format!("Not a Bitwarden JSON export: {}", e)))?;
+
+    if export.encrypted {
+        export = decrypt_export(&export, password)?;
+    }
+
+    // Nested folders are stored with their full path as the name
+    let folders: HashMap<&str, Vec<String>> = export
+        .folders
+        .iter()
+        .map(|f| (f.id.as_str(), f.name.split('/').map(str::to_string).collect()))
+        .collect();
+
+    let mut parsed = ParsedImport::default();
+
+    for (i, item) in export.items.into_iter().enumerate() {
+        let row = i + 1;
+
+        let Some(login) = item.login.filter(|_| item.kind == TYPE_LOGIN) else {
+            parsed.warn(row, format!("'{}' is not a login and was skipped", item.name));
+            continue;
+        };
+
+        let mut entry = ImportedEntry {
+            row,
+            label: item.name,
+            url_match: url_match(login.uris.first().and_then(|u| u.match_type)),
+            urls: login.uris.into_iter().filter_map(|u| u.uri).collect(),
+            name: login.username.unwrap_or_default(),
+            password: login.password.unwrap_or_default(),
+            notes: item.notes.unwrap_or_default(),
+            totp: login.totp,
+            favorite: item.favorite,
+            folder: item
+                .folder_id
+                .and_then(|id| folders.get(id.as_str()).cloned())
+                .unwrap_or_default(),
+            created_at: parse_date(&item.creation_date),
+            modified_at: parse_date(&item.revision_date),
+            ..Default::default()
+        };
+
+        for field in item.fields.iter().filter(|f| f.kind != FIELD_LINKED) {
+            entry.add_field(
+                field.name.as_deref().unwrap_or_default(),
+                field.value.as_deref().unwrap_or_default(),
+                field.kind == FIELD_HIDDEN,
+            );
+        }
+
+        for old in item.password_history.iter().flatten() {
+            entry.add_history(old.password.as_deref().unwrap_or_default(), parse_date(&old.last_used_date));
+        }
+
+        parsed.entries.push(entry);
+    }
+
+    Ok(parsed)
+}
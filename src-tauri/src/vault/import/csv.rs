@@ -0,0 +1,69 @@
+use super::{from_unix, import_error, ImportedEntry, ParsedImport};
+use crate::vault::vault::{VaultErrorKind, VaultResult};
+
+// Column names used by Chrome, Firefox, Bitwarden and LastPass, lowercased
+const LABEL: &[&str] = &["name", "title"];
+const URL: &[&str] = &["url", "login_uri", "website"];
+const USERNAME: &[&str] = &["username", "login_username", "user"];
+const PASSWORD: &[&str] = &["password", "login_password"];
+const NOTES: &[&str] = &["note", "notes", "extra"];
+const TOTP: &[&str] = &["totp", "login_totp", "otpauth"];
+const FOLDER: &[&str] = &["folder", "grouping"];
+const FAVORITE: &[&str] = &["favorite", "fav"];
+// Firefox, in milliseconds
+const CREATED: &[&str] = &["timecreated"];
+const MODIFIED: &[&str] = &["timepasswordchanged"];
+
+/// Parses a password CSV with a header row, columns are matched by name.
+pub fn parse(text: &str) -> VaultResult<ParsedImport> {
+    let mut reader = ::csv::ReaderBuilder::new().flexible(true).from_reader(text.as_bytes());
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| import_error(VaultErrorKind::Parse, format!("Not a CSV file: {}", e)))?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+
+    if column(PASSWORD).is_none() {
+        return Err(import_error(VaultErrorKind::Parse, "CSV file has no password column"));
+    }
+
+    let columns = [LABEL, URL, USERNAME, PASSWORD, NOTES, TOTP, FOLDER, FAVORITE, CREATED, MODIFIED].map(column);
+    let mut parsed = ParsedImport::default();
+
+    for (i, record) in reader.records().enumerate() {
+        // The header is the first row
+        let row = i + 2;
+
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                parsed.warn(row, format!("Row couldn't be read: {}", e));
+                continue;
+            }
+        };
+
+        let get = |n: usize| columns[n].and_then(|c| record.get(c)).unwrap_or_default().to_string();
+        let time = |n: usize| get(n).trim().parse::<i64>().ok().and_then(from_unix);
+
+        parsed.entries.push(ImportedEntry {
+            row,
+            label: get(0),
+            url: get(1),
+            name: get(2),
+            password: get(3),
+            notes: get(4),
+            totp: Some(get(5)),
+            folder: get(6).split(['/', '\\']).map(str::trim).filter(|f| !f.is_empty()).map(str::to_string).collect(),
+            favorite: matches!(get(7).trim(), "1" | "true" | "TRUE" | "True"),
+            created_at: time(8),
+            modified_at: time(9),
+            ..Default::default()
+        });
+    }
+
+    Ok(parsed)
+}
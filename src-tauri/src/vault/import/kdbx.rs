@@ -0,0 +1,122 @@
+use super::{keepass, ParsedImport};
+use crate::vault::kdbx;
+use crate::vault::vault::VaultResult;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// Opens a KDBX 4 database and reads it like the XML format.
+pub fn parse(data: &[u8], password: Option<&str>, key_file: Option<&Path>) -> VaultResult<ParsedImport> {
+    let mut key = kdbx::composite_key(password, key_file)?;
+    let opened = kdbx::open(data, &key);
+    key.zeroize();
+
+    let (mut xml, mut stream) = opened?;
+    let parsed = keepass::read(&xml, Some(&mut stream));
+    xml.zeroize();
+
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_file, ImportFormat, ParsedImport};
+    use crate::vault::entry::CustomFieldKind;
+    use crate::vault::vault::{VaultErrorKind, VaultResult};
+    use std::path::PathBuf;
+    use time::format_description::well_known::Rfc3339;
+    use time::OffsetDateTime;
+
+    // Written by tests/fixtures/kdbx/generate.py
+    const PASSWORD: &str = "fixture-password";
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/kdbx").join(name)
+    }
+
+    fn date(value: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(value, &Rfc3339).unwrap()
+    }
+
+    fn open(name: &str, password: Option<&str>, key_file: Option<&str>) -> VaultResult<ParsedImport> {
+        let key_file = key_file.map(fixture);
+        parse_file(ImportFormat::Kdbx, &fixture(name), password, key_file.as_deref())
+    }
+
+    /// Every fixture holds the same database, see `generate.py`.
+    fn assert_contents(parsed: &ParsedImport) {
+        let labels: Vec<&str> = parsed.entries.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(labels, ["Mail", "Bank"], "the recycle bin is left out");
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].message.contains("'Router' has no password"));
+
+        let mail = &parsed.entries[0];
+        assert_eq!(mail.name, "alice@example.com");
+        assert_eq!(mail.password, "current-mail-password");
+        assert_eq!(mail.url, "https://mail.example.com");
+        assert_eq!(mail.urls, ["https://webmail.example.com"]);
+        assert_eq!(mail.notes, "Main inbox\nSecond line");
+        assert_eq!(mail.tags, ["mail", "work"]);
+        assert_eq!(mail.folder, Vec::<String>::new());
+        assert_eq!(mail.modified_at, Some(date("2024-05-01T00:00:00Z")));
+        assert_eq!(
+            mail.totp.as_deref(),
+            Some("otpauth://totp/Mail:alice?secret=JBSWY3DPEHPK3PXP&issuer=Mail")
+        );
+
+        let fields: Vec<(&str, &str, bool)> = mail
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.value.as_str(), f.kind == CustomFieldKind::Hidden))
+            .collect();
+        assert_eq!(fields, [("PIN", "4321", true), ("Security question", "First pet?", false)]);
+
+        let history: Vec<_> = mail.password_history.iter().map(|h| (h.password.as_str(), h.replaced_at)).collect();
+        assert_eq!(
+            history,
+            [
+                ("second-mail-password", date("2024-05-01T00:00:00Z")),
+                ("first-mail-password", date("2022-06-01T00:00:00Z")),
+            ]
+        );
+
+        // Comes after the history and the recycle bin in the keystream
+        let bank = &parsed.entries[1];
+        assert_eq!(bank.folder, ["Banking", "EU"]);
+        assert_eq!(bank.name, "acct-001");
+        assert_eq!(bank.password, "bank-pässword-€");
+        assert_eq!(bank.fields.len(), 1);
+        assert_eq!((bank.fields[0].name.as_str(), bank.fields[0].value.as_str()), ("Card PIN", "9876"));
+        assert!(bank.fields[0].kind == CustomFieldKind::Hidden);
+    }
+
+    #[test]
+    fn opens_argon2d_with_aes() {
+        assert_contents(&open("argon2d-aes.kdbx", Some(PASSWORD), None).unwrap());
+    }
+
+    #[test]
+    fn opens_argon2id_with_chacha20() {
+        assert_contents(&open("argon2id-chacha20.kdbx", Some(PASSWORD), None).unwrap());
+    }
+
+    #[test]
+    fn opens_aes_kdf_with_aes() {
+        assert_contents(&open("aes-kdf-aes.kdbx", Some(PASSWORD), None).unwrap());
+    }
+
+    #[test]
+    fn opens_with_key_file_only() {
+        assert_contents(&open("key-file-only.kdbx", None, Some("key-file-only.keyx")).unwrap());
+
+        let without_key = open("key-file-only.kdbx", Some(PASSWORD), None).err().unwrap();
+        assert!(matches!(without_key.kind, VaultErrorKind::Auth));
+    }
+
+    #[test]
+    fn wrong_password_is_an_auth_error() {
+        for name in ["argon2d-aes.kdbx", "argon2id-chacha20.kdbx", "aes-kdf-aes.kdbx"] {
+            let error = open(name, Some("not-the-password"), None).err().unwrap();
+            assert!(matches!(error.kind, VaultErrorKind::Auth), "{}", name);
+        }
+    }
+}
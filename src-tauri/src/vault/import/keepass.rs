@@ -0,0 +1,192 @@
+use super::{import_error, ImportedEntry, ParsedImport};
+use crate::vault::kdbx::{self, InnerStream};
+use crate::vault::vault::{VaultErrorKind, VaultResult};
+use roxmltree::{Document, Node, NodeId};
+use std::collections::HashMap;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+// Strings with their own place on an entry, everything else becomes a custom field
+const STANDARD_KEYS: [&str; 5] = ["Title", "UserName", "Password", "URL", "Notes"];
+// KeePassXC keeps an otpauth URI, KeePass 2.47+ the bare secret
+const OTP_KEYS: [&str; 2] = ["otp", "TimeOtp-Secret-Base32"];
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(|n| n.text()).map(str::to_string)
+}
+
+fn parse_time(times: Option<Node>, name: &str) -> Option<OffsetDateTime> {
+    let value = child_text(times?, name)?;
+    OffsetDateTime::parse(&value, &Rfc3339).ok().or_else(|| kdbx::decode_time(&value))
+}
+
+fn is_protected(value: Node) -> bool {
+    value.attribute("ProtectInMemory") == Some("True") || value.attribute("Protected") == Some("True")
+}
+
+/// Text of the document, with the protected values of a KDBX file decrypted.
+struct Values {
+    decrypted: HashMap<NodeId, String>,
+}
+
+impl Values {
+    /// Protected values share one keystream, so all of them are decrypted
+    /// up front in document order, history and recycle bin included.
+    fn new(document: &Document, stream: Option<&mut InnerStream>) -> VaultResult<Self> {
+        let mut decrypted = HashMap::new();
+
+        if let Some(stream) = stream {
+            let protected = document
+                .descendants()
+                .filter(|n| n.has_tag_name("Value") && n.attribute("Protected") == Some("True"));
+
+            for value in protected {
+                decrypted.insert(value.id(), stream.decrypt(value.text().unwrap_or_default())?);
+            }
+        }
+
+        Ok(Values { decrypted })
+    }
+
+    fn text(&self, value: Option<Node>) -> String {
+        value
+            .and_then(|v| self.decrypted.get(&v.id()).cloned().or_else(|| v.text().map(str::to_string)))
+            .unwrap_or_default()
+    }
+
+    fn string(&self, entry: Node, key: &str) -> String {
+        let string = entry
+            .children()
+            .filter(|n| n.has_tag_name("String"))
+            .find(|n| child_text(*n, "Key").as_deref() == Some(key));
+
+        self.text(string.and_then(|s| child(s, "Value")))
+    }
+}
+
+fn read_entry(node: Node, row: usize, folder: &[String], values: &Values) -> ImportedEntry {
+    let mut entry = ImportedEntry {
+        row,
+        folder: folder.to_vec(),
+        created_at: parse_time(child(node, "Times"), "CreationTime"),
+        modified_at: parse_time(child(node, "Times"), "LastModificationTime"),
+        tags: child_text(node, "Tags")
+            .map(|t| t.split([';', ',']).map(str::to_string).collect())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    for string in node.children().filter(|n| n.has_tag_name("String")) {
+        let Some(key) = child_text(string, "Key") else {
+            continue;
+        };
+        let value = child(string, "Value");
+        let text = values.text(value);
+
+        match key.as_str() {
+            "Title" => entry.label = text,
+            "UserName" => entry.name = text,
+            "Password" => entry.password = text,
+            "URL" => entry.url = text,
+            "Notes" => entry.notes = text,
+            key if OTP_KEYS.contains(&key) => entry.totp = Some(text),
+            // Additional URLs as written by KeePassXC and Keepass2Android
+            key if key.starts_with("KP2A_URL") => entry.urls.push(text),
+            key if !STANDARD_KEYS.contains(&key) && !key.starts_with("TimeOtp-") => {
+                entry.add_field(key, &text, value.is_some_and(is_protected))
+            }
+            _ => {}
+        }
+    }
+
+    read_history(node, &mut entry, values);
+
+    entry
+}
+
+/// History entries are full snapshots. A password counts as replaced when
+/// the next snapshot, or the entry itself, has a different one.
+fn read_history(node: Node, entry: &mut ImportedEntry, values: &Values) {
+    let Some(history) = child(node, "History") else {
+        return;
+    };
+
+    let mut versions: Vec<(String, Option<OffsetDateTime>)> = history
+        .children()
+        .filter(|n| n.has_tag_name("Entry"))
+        .map(|e| (values.string(e, "Password"), parse_time(child(e, "Times"), "LastModificationTime")))
+        .collect();
+
+    versions.sort_by_key(|(_, modified_at)| *modified_at);
+    versions.push((entry.password.clone(), entry.modified_at));
+
+    for pair in versions.windows(2) {
+        if pair[0].0 != pair[1].0 {
+            entry.add_history(&pair[0].0, pair[1].1);
+        }
+    }
+}
+
+struct Context {
+    recycle_bin: Option<String>,
+    values: Values,
+    parsed: ParsedImport,
+    row: usize,
+}
+
+/// Walks a group and its subgroups. The folder path leaves out the root
+/// group, which only carries the database name.
+fn read_group(group: Node, path: Vec<String>, context: &mut Context) {
+    for node in group.children().filter(Node::is_element) {
+        match node.tag_name().name() {
+            "Entry" => {
+                context.row += 1;
+                let entry = read_entry(node, context.row, &path, &context.values);
+                context.parsed.entries.push(entry);
+            }
+            "Group" => {
+                if context.recycle_bin.is_some() && child_text(node, "UUID") == context.recycle_bin {
+                    continue;
+                }
+
+                let mut path = path.clone();
+                path.push(child_text(node, "Name").unwrap_or_else(|| "Group".into()));
+                read_group(node, path, context);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads the XML of a KeePass 2 database. `stream` decrypts protected
+/// values and is only set for KDBX files.
+pub fn read(text: &str, stream: Option<&mut InnerStream>) -> VaultResult<ParsedImport> {
+    let document = Document::parse(text)
+        .map_err(|e| import_error(VaultErrorKind::Parse, format!("Not a KeePass XML export: {}", e)))?;
+
+    let file = document.root_element();
+    let root = Some(file)
+        .filter(|f| f.has_tag_name("KeePassFile"))
+        .and_then(|f| child(f, "Root"))
+        .and_then(|r| child(r, "Group"))
+        .ok_or_else(|| import_error(VaultErrorKind::Parse, "Not a KeePass XML export: no root group"))?;
+
+    let mut context = Context {
+        recycle_bin: child(file, "Meta").and_then(|m| child_text(m, "RecycleBinUUID")),
+        values: Values::new(&document, stream)?,
+        parsed: ParsedImport::default(),
+        row: 0,
+    };
+    read_group(root, Vec::new(), &mut context);
+
+    Ok(context.parsed)
+}
+
+/// Parses the KeePass 2 XML format.
+pub fn parse(text: &str) -> VaultResult<ParsedImport> {
+    read(text, None)
+}
@@ -0,0 +1,240 @@
+pub mod bitwarden;
+pub mod csv;
+pub mod kdbx;
+pub mod keepass;
+pub mod nebula;
+pub mod onepassword;
+
+use crate::vault::entry::{CustomField, CustomFieldKind, Entry, PasswordHistoryEntry, UrlMatchMode, MAX_PASSWORD_HISTORY};
+use crate::vault::search;
+use crate::vault::strength;
+use crate::vault::totp::TotpConfig;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum ImportFormat {
+    // Plain or password protected JSON export
+    Bitwarden,
+    KeePassXml,
+    // KeePass 2 database, opened with a password and/or key file
+    Kdbx,
+    // .1pux archive
+    OnePassword,
+    // Encrypted export of another Nebula vault
+    Nebula,
+    // Chrome, Firefox and other exports with a header row
+    Csv,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportWarning {
+    // Position of the record in the source, starting at 1
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub imported: usize,
+    // Skipped because the vault already holds the same login
+    pub duplicates: usize,
+    pub warnings: Vec<ImportWarning>,
+}
+
+/// A login read from another password manager, before it becomes an entry.
+#[derive(Default)]
+pub struct ImportedEntry {
+    pub row: usize,
+    pub label: String,
+    pub url: String,
+    pub urls: Vec<String>,
+    pub url_match: UrlMatchMode,
+    pub name: String,
+    pub password: String,
+    pub notes: String,
+    pub totp: Option<String>,
+    pub favorite: bool,
+    pub tags: Vec<String>,
+    // Folder names from the top down, created when missing
+    pub folder: Vec<String>,
+    pub fields: Vec<CustomField>,
+    // Newest first, like on entries
+    pub password_history: Vec<PasswordHistoryEntry>,
+    pub created_at: Option<OffsetDateTime>,
+    pub modified_at: Option<OffsetDateTime>,
+}
+
+#[derive(Default)]
+pub struct ParsedImport {
+    pub entries: Vec<ImportedEntry>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl ParsedImport {
+    pub fn warn(&mut self, row: usize, message: impl Into<String>) {
+        self.warnings.push(ImportWarning {
+            row,
+            message: message.into(),
+        });
+    }
+}
+
+impl ImportedEntry {
+    pub fn add_field(&mut self, name: &str, value: &str, hidden: bool) {
+        if value.is_empty() {
+            return;
+        }
+
+        self.fields.push(CustomField {
+            id: Uuid::new_v4(),
+            name: if name.trim().is_empty() { "Field".into() } else { name.trim().to_string() },
+            kind: if hidden { CustomFieldKind::Hidden } else { CustomFieldKind::Text },
+            value: value.to_string(),
+        });
+    }
+
+    /// Records a previous password, ones without a date count as replaced at import.
+    pub fn add_history(&mut self, password: &str, replaced_at: Option<OffsetDateTime>) {
+        if password.is_empty() {
+            return;
+        }
+
+        self.password_history.push(PasswordHistoryEntry {
+            id: Uuid::new_v4(),
+            password: password.to_string(),
+            password_strength: strength::estimate(password),
+            replaced_at: replaced_at.unwrap_or_else(OffsetDateTime::now_utc),
+        });
+    }
+
+    /// Host, username and password, the same login imported twice has the same key.
+    pub fn dedupe_key(&self) -> (String, String, String) {
+        login_key(&self.url, &self.name, &self.password)
+    }
+
+    pub fn into_entry(self, folder_id: Option<Uuid>) -> Entry {
+        let now = OffsetDateTime::now_utc();
+        let created_at = self.created_at.unwrap_or(now);
+
+        Entry {
+            id: Uuid::new_v4(),
+            created_at,
+            modified_at: self.modified_at.unwrap_or(created_at),
+            last_used: None,
+            uses: 0,
+            label: self.label,
+            url: self.url,
+            name: self.name,
+            password: self.password,
+            password_strength: Default::default(),
+            favorite: self.favorite,
+            fields: self.fields,
+            notes: self.notes,
+            totp: self.totp,
+            password_history: self.password_history,
+            deleted_at: None,
+            tags: self.tags,
+            folder_id,
+            urls: self.urls,
+            url_match: self.url_match,
+        }
+    }
+}
+
+pub fn login_key(url: &str, name: &str, password: &str) -> (String, String, String) {
+    (
+        search::url_host(url).unwrap_or_default().to_lowercase(),
+        name.trim().to_lowercase(),
+        password.to_string(),
+    )
+}
+
+pub fn import_error(kind: VaultErrorKind, message: impl Into<String>) -> VaultError {
+    VaultError {
+        kind,
+        severity: VaultErrorSeverity::Soft,
+        message: message.into(),
+        code: "E_IMPORT",
+    }
+}
+
+/// Fills in what the formats leave out and drops logins that can't be
+/// stored, with a warning for each.
+fn finish(mut parsed: ParsedImport) -> ParsedImport {
+    let entries = std::mem::take(&mut parsed.entries);
+
+    for mut entry in entries {
+        let urls: Vec<String> = std::iter::once(entry.url.clone())
+            .chain(entry.urls.drain(..))
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty())
+            .collect();
+        entry.url = urls.first().cloned().unwrap_or_default();
+        entry.urls = urls.into_iter().skip(1).collect();
+
+        if entry.label.trim().is_empty() {
+            entry.label = search::url_host(&entry.url)
+                .or_else(|| Some(entry.name.clone()).filter(|n| !n.trim().is_empty()))
+                .unwrap_or_else(|| "Imported login".into());
+        }
+
+        if entry.password.is_empty() {
+            parsed.warn(entry.row, format!("'{}' has no password and was skipped", entry.label));
+            continue;
+        }
+
+        // Newest first, each password once and not the current one, capped like on entries
+        entry.password_history.sort_by_key(|h| std::cmp::Reverse(h.replaced_at));
+        let mut seen = HashSet::from([entry.password.clone()]);
+        entry.password_history.retain(|h| seen.insert(h.password.clone()));
+        entry.password_history.truncate(MAX_PASSWORD_HISTORY);
+
+        if let Some(totp) = entry.totp.take().filter(|t| !t.trim().is_empty()) {
+            match TotpConfig::parse(&totp) {
+                Ok(_) => entry.totp = Some(totp.trim().to_string()),
+                Err(e) => parsed.warn(entry.row, format!("Dropped the TOTP secret of '{}': {}", entry.label, e)),
+            }
+        }
+
+        parsed.entries.push(entry);
+    }
+
+    parsed
+}
+
+/// Reads and parses an export. `password` is only used by encrypted Bitwarden
+/// and Nebula exports and KeePass databases, `key_file` only by the latter.
+pub fn parse_file(format: ImportFormat, path: &Path, password: Option<&str>, key_file: Option<&Path>) -> VaultResult<ParsedImport> {
+    let data = std::fs::read(path).map_err(|e| import_error(VaultErrorKind::IO, format!("Couldn't read import file: {}", e)))?;
+
+    let text = || {
+        // Exports written on Windows often start with a byte order mark
+        let text = std::str::from_utf8(&data).map_err(|_| import_error(VaultErrorKind::Parse, "Import file is not valid UTF-8"))?;
+        Ok::<_, VaultError>(text.trim_start_matches('\u{feff}'))
+    };
+
+    let parsed = match format {
+        ImportFormat::Bitwarden => bitwarden::parse(text()?, password)?,
+        ImportFormat::KeePassXml => keepass::parse(text()?)?,
+        ImportFormat::Kdbx => kdbx::parse(&data, password, key_file)?,
+        ImportFormat::OnePassword => onepassword::parse(&data)?,
+        ImportFormat::Csv => csv::parse(text()?)?,
+        ImportFormat::Nebula => nebula::parse(text()?, password)?,
+    };
+
+    Ok(finish(parsed))
+}
+
+/// Timestamps given as seconds or milliseconds since the epoch.
+pub fn from_unix(value: i64) -> Option<OffsetDateTime> {
+    // Anything past the year 3000 in seconds has to be milliseconds
+    let seconds = if value > 32_503_680_000 { value / 1000 } else { value };
+    OffsetDateTime::from_unix_timestamp(seconds).ok().filter(|_| value > 0)
+}
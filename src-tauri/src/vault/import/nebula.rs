@@ -0,0 +1,40 @@
+use super::{import_error, ImportedEntry, ParsedImport};
+use crate::vault::export::{nebula, ExportData};
+use crate::vault::vault::{VaultErrorKind, VaultResult};
+
+/// Reads an encrypted Nebula export. History, tags and custom fields are kept,
+/// folders are matched by their path like in the other formats.
+pub fn parse(text: &str, password: Option<&str>) -> VaultResult<ParsedImport> {
+    let password = password.ok_or_else(|| import_error(VaultErrorKind::Auth, "This export is protected by a password"))?;
+    let payload = nebula::open(text, password)?;
+
+    let data = ExportData {
+        entries: payload.entries.iter().collect(),
+        folders: &payload.folders,
+    };
+
+    let mut parsed = ParsedImport::default();
+
+    for (i, entry) in payload.entries.iter().enumerate() {
+        parsed.entries.push(ImportedEntry {
+            row: i + 1,
+            label: entry.label.clone(),
+            url: entry.url.clone(),
+            urls: entry.urls.clone(),
+            url_match: entry.url_match,
+            name: entry.name.clone(),
+            password: entry.password.clone(),
+            notes: entry.notes.clone(),
+            totp: entry.totp.clone(),
+            favorite: entry.favorite,
+            tags: entry.tags.clone(),
+            folder: data.folder_path(entry.folder_id).into_iter().map(str::to_string).collect(),
+            fields: entry.fields.clone(),
+            password_history: entry.password_history.clone(),
+            created_at: Some(entry.created_at),
+            modified_at: Some(entry.modified_at),
+        });
+    }
+
+    Ok(parsed)
+}
@@ -0,0 +1,186 @@
+use super::{from_unix, import_error, ImportedEntry, ParsedImport};
+use crate::vault::vault::{VaultErrorKind, VaultResult};
+use serde_json::Value;
+use std::io::{Cursor, Read};
+
+const EXPORT_DATA: &str = "export.data";
+const CATEGORY_LOGIN: &str = "001";
+const CATEGORY_PASSWORD: &str = "005";
+// Far beyond any real export, stops a small archive from inflating without limit
+const MAX_EXPORT_DATA_SIZE: u64 = 64 * 1024 * 1024;
+
+fn text<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn list<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value.get(key).and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Section fields hold their value under a key naming its type.
+fn read_section_field(entry: &mut ImportedEntry, field: &Value) {
+    let name = text(field, "title");
+    let Some((kind, value)) = field.get("value").and_then(Value::as_object).and_then(|v| v.iter().next()) else {
+        return;
+    };
+
+    match (kind.as_str(), value) {
+        ("totp", Value::String(secret)) => entry.totp = Some(secret.clone()),
+        ("concealed", Value::String(secret)) => entry.add_field(name, secret, true),
+        ("string" | "url" | "phone", Value::String(value)) => entry.add_field(name, value, false),
+        ("email", email) => entry.add_field(name, text(email, "email_address"), false),
+        _ => {}
+    }
+}
+
+fn read_item(item: &Value, row: usize, vault_name: &str) -> ImportedEntry {
+    let overview = item.get("overview").unwrap_or(&Value::Null);
+    let details = item.get("details").unwrap_or(&Value::Null);
+
+    let mut entry = ImportedEntry {
+        row,
+        label: text(overview, "title").to_string(),
+        url: text(overview, "url").to_string(),
+        urls: list(overview, "urls").iter().map(|u| text(u, "url").to_string()).collect(),
+        notes: text(details, "notesPlain").to_string(),
+        password: text(details, "password").to_string(),
+        favorite: item.get("favIndex").and_then(Value::as_i64).is_some_and(|i| i > 0),
+        tags: list(overview, "tags").iter().filter_map(Value::as_str).map(str::to_string).collect(),
+        folder: vec![vault_name.to_string()],
+        created_at: item.get("createdAt").and_then(Value::as_i64).and_then(from_unix),
+        modified_at: item.get("updatedAt").and_then(Value::as_i64).and_then(from_unix),
+        ..Default::default()
+    };
+
+    // The main URL shows up again in the list
+    entry.urls.retain(|u| *u != entry.url);
+
+    for field in list(details, "loginFields") {
+        match text(field, "designation") {
+            "username" => entry.name = text(field, "value").to_string(),
+            "password" => entry.password = text(field, "value").to_string(),
+            _ => {}
+        }
+    }
+
+    for section in list(details, "sections") {
+        for field in list(section, "fields") {
+            read_section_field(&mut entry, field);
+        }
+    }
+
+    entry
+}
+
+/// Unpacks `export.data`, refusing to inflate more than `limit` bytes. The
+/// size an archive declares can't be trusted, so reading stops there too.
+fn read_export_data(data: &[u8], limit: u64) -> VaultResult<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))
+        .map_err(|e| import_error(VaultErrorKind::Parse, format!("Not a 1Password export: {}", e)))?;
+
+    let file = archive
+        .by_name(EXPORT_DATA)
+        .map_err(|_| import_error(VaultErrorKind::Parse, "Not a 1Password export: export.data is missing"))?;
+
+    let too_large = || import_error(VaultErrorKind::Parse, format!("export.data is larger than {} MiB", limit / (1024 * 1024)));
+    if file.size() > limit {
+        return Err(too_large());
+    }
+
+    let mut content = String::new();
+    file.take(limit + 1)
+        .read_to_string(&mut content)
+        .map_err(|e| import_error(VaultErrorKind::IO, format!("Couldn't read export.data: {}", e)))?;
+
+    if content.len() as u64 > limit {
+        return Err(too_large());
+    }
+
+    Ok(content)
+}
+
+/// Reads a `.1pux` archive. Every 1Password vault becomes a folder, archived
+/// items and anything besides logins and passwords are skipped.
+pub fn parse(data: &[u8]) -> VaultResult<ParsedImport> {
+    let content = read_export_data(data, MAX_EXPORT_DATA_SIZE)?;
+
+    let export: Value = serde_json::from_str(&content)
+        .map_err(|e| import_error(VaultErrorKind::Parse, format!("export.data is not valid: {}", e)))?;
+
+    let mut parsed = ParsedImport::default();
+    let mut row = 0;
+
+    for account in list(&export, "accounts") {
+        for vault in list(account, "vaults") {
+            let vault_name = vault.get("attrs").map_or("1Password", |a| text(a, "name"));
+
+            for item in list(vault, "items") {
+                row += 1;
+                let title = item.get("overview").map_or("", |o| text(o, "title"));
+
+                if text(item, "state") == "archived" {
+                    parsed.warn(row, format!("'{}' is archived and was skipped", title));
+                    continue;
+                }
+
+                match text(item, "categoryUuid") {
+                    CATEGORY_LOGIN | CATEGORY_PASSWORD => parsed.entries.push(read_item(item, row, vault_name)),
+                    _ => parsed.warn(row, format!("'{}' is not a login and was skipped", title)),
+                }
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn archive(export_data: &str) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file(EXPORT_DATA, SimpleFileOptions::default()).unwrap();
+        writer.write_all(export_data.as_bytes()).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn export_data_over_the_limit_is_refused() {
+        let data = archive(&" ".repeat(4096));
+
+        assert_eq!(read_export_data(&data, 4096).unwrap().len(), 4096);
+
+        let error = read_export_data(&data, 4095).err().unwrap();
+        assert!(matches!(error.kind, VaultErrorKind::Parse));
+    }
+
+    #[test]
+    fn reads_logins_from_archive() {
+        let export = serde_json::json!({
+            "accounts": [{
+                "vaults": [{
+                    "attrs": { "name": "Private" },
+                    "items": [{
+                        "categoryUuid": CATEGORY_LOGIN,
+                        "overview": { "title": "Mail", "url": "https://mail.example.com" },
+                        "details": {
+                            "loginFields": [
+                                { "designation": "username", "value": "alice" },
+                                { "designation": "password", "value": "mail-password" }
+                            ]
+                        }
+                    }]
+                }]
+            }]
+        });
+
+        let parsed = parse(&archive(&export.to_string())).unwrap();
+        assert_eq!(parsed.entries.len(), 1);
+        assert_eq!(parsed.entries[0].name, "alice");
+        assert_eq!(parsed.entries[0].password, "mail-password");
+        assert_eq!(parsed.entries[0].folder, ["Private"]);
+    }
+}
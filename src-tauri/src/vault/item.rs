@@ -0,0 +1,476 @@
+use crate::vault::entry::{Entry, EntryPublic};
+use base64::{engine::general_purpose::{STANDARD, STANDARD_NO_PAD}, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Bookkeeping shared by every item type. Logins carry the same fields
+/// directly on `Entry`, which predates the other types.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemMeta {
+    pub id: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub modified_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub last_used: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub uses: u32,
+    pub label: String,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 Item types                                 */
+/* -------------------------------------------------------------------------- */
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CardItem {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub cardholder_name: String,
+    pub brand: String,
+    pub number: String,
+    pub exp_month: String,
+    pub exp_year: String,
+    pub cvv: String,
+    pub pin: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityItem {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub phone: String,
+    pub company: String,
+    pub address: String,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+    pub country: String,
+    pub ssn: String,
+    pub passport_number: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub enum WifiSecurity {
+    None,
+    Wep,
+    WpaPersonal,
+    WpaEnterprise,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WifiItem {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub ssid: String,
+    pub security: WifiSecurity,
+    pub password: String,
+    pub hidden: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LicenseItem {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub product: String,
+    pub licensed_to: String,
+    pub email: String,
+    pub license_key: String,
+    pub purchase_date: String,
+    pub expires_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SshKeyItem {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub public_key: String,
+    pub private_key: String,
+    pub passphrase: String,
+}
+
+/// Everything the vault can hold, tagged with its type in the payload.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum Item {
+    Login(Entry),
+    Card(CardItem),
+    Identity(IdentityItem),
+    Wifi(WifiItem),
+    License(LicenseItem),
+    SshKey(SshKeyItem),
+}
+
+/* -------------------------------------------------------------------------- */
+/*                             Public projections                             */
+/* -------------------------------------------------------------------------- */
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CardPublic {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub cardholder_name: String,
+    pub brand: String,
+    pub last_digits: String,
+    pub exp_month: String,
+    pub exp_year: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityPublic {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub phone: String,
+    pub company: String,
+    pub address: String,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+    pub country: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WifiPublic {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub ssid: String,
+    pub security: WifiSecurity,
+    pub hidden: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LicensePublic {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub product: String,
+    pub licensed_to: String,
+    pub email: String,
+    pub purchase_date: String,
+    pub expires_at: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SshKeyPublic {
+    #[serde(flatten)]
+    pub meta: ItemMeta,
+    pub public_key: String,
+    pub fingerprint: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "type")]
+pub enum ItemPublic {
+    Login(EntryPublic),
+    Card(CardPublic),
+    Identity(IdentityPublic),
+    Wifi(WifiPublic),
+    License(LicensePublic),
+    SshKey(SshKeyPublic),
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                   Updates                                  */
+/* -------------------------------------------------------------------------- */
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCard {
+    pub label: Option<String>,
+    pub favorite: Option<bool>,
+    pub cardholder_name: Option<String>,
+    pub brand: Option<String>,
+    pub number: Option<String>,
+    pub exp_month: Option<String>,
+    pub exp_year: Option<String>,
+    pub cvv: Option<String>,
+    pub pin: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateIdentity {
+    pub label: Option<String>,
+    pub favorite: Option<bool>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub company: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub ssn: Option<String>,
+    pub passport_number: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWifi {
+    pub label: Option<String>,
+    pub favorite: Option<bool>,
+    pub ssid: Option<String>,
+    pub security: Option<WifiSecurity>,
+    pub password: Option<String>,
+    pub hidden: Option<bool>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateLicense {
+    pub label: Option<String>,
+    pub favorite: Option<bool>,
+    pub product: Option<String>,
+    pub licensed_to: Option<String>,
+    pub email: Option<String>,
+    pub license_key: Option<String>,
+    pub purchase_date: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSshKey {
+    pub label: Option<String>,
+    pub favorite: Option<bool>,
+    pub public_key: Option<String>,
+    pub private_key: Option<String>,
+    pub passphrase: Option<String>,
+}
+
+/// Partial update of a non-login item. Logins keep using `UpdateEntry`.
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum UpdateItem {
+    Card(UpdateCard),
+    Identity(UpdateIdentity),
+    Wifi(UpdateWifi),
+    License(UpdateLicense),
+    SshKey(UpdateSshKey),
+}
+
+fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+    if let Some(value) = value {
+        *target = value.clone();
+    }
+}
+
+impl Item {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Item::Login(e) => e.id,
+            Item::Card(i) => i.meta.id,
+            Item::Identity(i) => i.meta.id,
+            Item::Wifi(i) => i.meta.id,
+            Item::License(i) => i.meta.id,
+            Item::SshKey(i) => i.meta.id,
+        }
+    }
+
+    pub fn as_login(&self) -> Option<&Entry> {
+        match self {
+            Item::Login(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Only logins can be in the trash, other item types are deleted right away.
+    pub fn is_trashed(&self) -> bool {
+        self.as_login().is_some_and(|e| e.deleted_at.is_some())
+    }
+
+    pub fn as_login_mut(&mut self) -> Option<&mut Entry> {
+        match self {
+            Item::Login(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    fn meta_mut(&mut self) -> Option<&mut ItemMeta> {
+        match self {
+            Item::Login(_) => None,
+            Item::Card(i) => Some(&mut i.meta),
+            Item::Identity(i) => Some(&mut i.meta),
+            Item::Wifi(i) => Some(&mut i.meta),
+            Item::License(i) => Some(&mut i.meta),
+            Item::SshKey(i) => Some(&mut i.meta),
+        }
+    }
+
+    /// Marks the item as used now and returns the new use count.
+    pub fn record_use(&mut self, now: OffsetDateTime) -> u32 {
+        let (last_used, uses) = match self {
+            Item::Login(e) => (&mut e.last_used, &mut e.uses),
+            other => {
+                let meta = other.meta_mut().expect("non-login items have metadata");
+                (&mut meta.last_used, &mut meta.uses)
+            }
+        };
+
+        *last_used = Some(now);
+        *uses += 1;
+        *uses
+    }
+
+    /// Value of a field that is withheld from the public projection.
+    /// Field names match the camelCase keys sent to the client.
+    pub fn secret(&self, field: &str) -> Option<String> {
+        let value = match (self, field) {
+            (Item::Login(e), "password") => &e.password,
+            (Item::Card(i), "number") => &i.number,
+            (Item::Card(i), "cvv") => &i.cvv,
+            (Item::Card(i), "pin") => &i.pin,
+            (Item::Identity(i), "ssn") => &i.ssn,
+            (Item::Identity(i), "passportNumber") => &i.passport_number,
+            (Item::Wifi(i), "password") => &i.password,
+            (Item::License(i), "licenseKey") => &i.license_key,
+            (Item::SshKey(i), "privateKey") => &i.private_key,
+            (Item::SshKey(i), "passphrase") => &i.passphrase,
+            _ => return None,
+        };
+
+        Some(value.clone())
+    }
+
+    pub fn apply(&mut self, update: &UpdateItem) -> Result<(), String> {
+        match (&mut *self, update) {
+            (Item::Card(i), UpdateItem::Card(u)) => {
+                set(&mut i.meta.label, &u.label);
+                set(&mut i.meta.favorite, &u.favorite);
+                set(&mut i.cardholder_name, &u.cardholder_name);
+                set(&mut i.brand, &u.brand);
+                set(&mut i.number, &u.number);
+                set(&mut i.exp_month, &u.exp_month);
+                set(&mut i.exp_year, &u.exp_year);
+                set(&mut i.cvv, &u.cvv);
+                set(&mut i.pin, &u.pin);
+            }
+            (Item::Identity(i), UpdateItem::Identity(u)) => {
+                set(&mut i.meta.label, &u.label);
+                set(&mut i.meta.favorite, &u.favorite);
+                set(&mut i.first_name, &u.first_name);
+                set(&mut i.last_name, &u.last_name);
+                set(&mut i.email, &u.email);
+                set(&mut i.phone, &u.phone);
+                set(&mut i.company, &u.company);
+                set(&mut i.address, &u.address);
+                set(&mut i.city, &u.city);
+                set(&mut i.state, &u.state);
+                set(&mut i.postal_code, &u.postal_code);
+                set(&mut i.country, &u.country);
+                set(&mut i.ssn, &u.ssn);
+                set(&mut i.passport_number, &u.passport_number);
+            }
+            (Item::Wifi(i), UpdateItem::Wifi(u)) => {
+                set(&mut i.meta.label, &u.label);
+                set(&mut i.meta.favorite, &u.favorite);
+                set(&mut i.ssid, &u.ssid);
+                set(&mut i.security, &u.security);
+                set(&mut i.password, &u.password);
+                set(&mut i.hidden, &u.hidden);
+            }
+            (Item::License(i), UpdateItem::License(u)) => {
+                set(&mut i.meta.label, &u.label);
+                set(&mut i.meta.favorite, &u.favorite);
+                set(&mut i.product, &u.product);
+                set(&mut i.licensed_to, &u.licensed_to);
+                set(&mut i.email, &u.email);
+                set(&mut i.license_key, &u.license_key);
+                set(&mut i.purchase_date, &u.purchase_date);
+                set(&mut i.expires_at, &u.expires_at);
+            }
+            (Item::SshKey(i), UpdateItem::SshKey(u)) => {
+                set(&mut i.meta.label, &u.label);
+                set(&mut i.meta.favorite, &u.favorite);
+                set(&mut i.public_key, &u.public_key);
+                set(&mut i.private_key, &u.private_key);
+                set(&mut i.passphrase, &u.passphrase);
+            }
+            _ => return Err("Update doesn't match the item type".into()),
+        }
+
+        if let Some(meta) = self.meta_mut() {
+            meta.modified_at = OffsetDateTime::now_utc();
+        }
+
+        Ok(())
+    }
+}
+
+/// OpenSSH style `SHA256:...` fingerprint of a public key line.
+fn ssh_fingerprint(public_key: &str) -> Option<String> {
+    let blob = STANDARD.decode(public_key.split_whitespace().nth(1)?).ok()?;
+    Some(format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(&blob))))
+}
+
+impl From<&Item> for ItemPublic {
+    fn from(item: &Item) -> Self {
+        match item {
+            Item::Login(e) => ItemPublic::Login(EntryPublic::from(e)),
+            Item::Card(i) => {
+                let digits: Vec<char> = i.number.chars().filter(char::is_ascii_digit).collect();
+
+                ItemPublic::Card(CardPublic {
+                    meta: i.meta.clone(),
+                    cardholder_name: i.cardholder_name.clone(),
+                    brand: i.brand.clone(),
+                    last_digits: digits[digits.len().saturating_sub(4)..].iter().collect(),
+                    exp_month: i.exp_month.clone(),
+                    exp_year: i.exp_year.clone(),
+                })
+            }
+            Item::Identity(i) => ItemPublic::Identity(IdentityPublic {
+                meta: i.meta.clone(),
+                first_name: i.first_name.clone(),
+                last_name: i.last_name.clone(),
+                email: i.email.clone(),
+                phone: i.phone.clone(),
+                company: i.company.clone(),
+                address: i.address.clone(),
+                city: i.city.clone(),
+                state: i.state.clone(),
+                postal_code: i.postal_code.clone(),
+                country: i.country.clone(),
+            }),
+            Item::Wifi(i) => ItemPublic::Wifi(WifiPublic {
+                meta: i.meta.clone(),
+                ssid: i.ssid.clone(),
+                security: i.security.clone(),
+                hidden: i.hidden,
+            }),
+            Item::License(i) => ItemPublic::License(LicensePublic {
+                meta: i.meta.clone(),
+                product: i.product.clone(),
+                licensed_to: i.licensed_to.clone(),
+                email: i.email.clone(),
+                purchase_date: i.purchase_date.clone(),
+                expires_at: i.expires_at.clone(),
+            }),
+            Item::SshKey(i) => ItemPublic::SshKey(SshKeyPublic {
+                meta: i.meta.clone(),
+                public_key: i.public_key.clone(),
+                fingerprint: ssh_fingerprint(&i.public_key),
+            }),
+        }
+    }
+}
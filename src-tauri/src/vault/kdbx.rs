@@ -0,0 +1,622 @@
+use crate::vault::crypto::{check_argon2_costs, KdfParams};
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use aes::cipher::{generic_array::GenericArray, BlockEncrypt};
+use aes::Aes256;
+use argon2::password_hash::rand_core::RngCore;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyInit, KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use chacha20poly1305::aead::OsRng;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use time::OffsetDateTime;
+use zeroize::Zeroize;
+
+const SIGNATURE_1: u32 = 0x9AA2_D903;
+const SIGNATURE_2: u32 = 0xB54B_FB67;
+// 4.0, readable by every KDBX 4 capable client
+const VERSION: u32 = 0x0004_0000;
+
+const CIPHER_AES256: [u8; 16] = uuid(0x31c1f2e6_bf71_4350_be58_05216afc5aff);
+const CIPHER_CHACHA20: [u8; 16] = uuid(0xd6038a2b_8b6f_4cb5_a524_339a31dbb59a);
+const KDF_AES: [u8; 16] = uuid(0xc9d9f39a_628a_4460_bf74_0d08c18a4fea);
+// Same transform, KeePassXC's id for it in KDBX 4 files
+const KDF_AES_KDBX4: [u8; 16] = uuid(0x7c02bb82_79a7_4ac0_927d_114a00648238);
+const KDF_ARGON2D: [u8; 16] = uuid(0xef636ddf_8c29_444b_91f7_a9a403e30a0c);
+const KDF_ARGON2ID: [u8; 16] = uuid(0x9e298b19_56db_4773_b23d_fc3ec6f0a1e6);
+
+// Outer header fields
+const HEADER_END: u8 = 0;
+const HEADER_CIPHER: u8 = 2;
+const HEADER_COMPRESSION: u8 = 3;
+const HEADER_MASTER_SEED: u8 = 4;
+const HEADER_IV: u8 = 7;
+const HEADER_KDF: u8 = 11;
+
+// Inner header fields
+const INNER_END: u8 = 0;
+const INNER_STREAM_ID: u8 = 1;
+const INNER_STREAM_KEY: u8 = 2;
+const INNER_STREAM_CHACHA20: u32 = 3;
+
+// A few seconds of AES-KDF even on fast machines, more isn't a real database
+const AES_KDF_MAX_ROUNDS: u64 = 100_000_000;
+
+const COMPRESSION_GZIP: u32 = 1;
+const BLOCK_SIZE: usize = 1024 * 1024;
+// Seconds between 0001-01-01, where KDBX 4 times start, and the unix epoch
+const EPOCH_OFFSET: i64 = 62_135_596_800;
+
+const fn uuid(value: u128) -> [u8; 16] {
+    value.to_be_bytes()
+}
+
+pub fn kdbx_error(kind: VaultErrorKind, message: impl Into<String>) -> VaultError {
+    VaultError {
+        kind,
+        severity: VaultErrorSeverity::Soft,
+        message: message.into(),
+        code: "E_KDBX",
+    }
+}
+
+fn corrupted() -> VaultError {
+    kdbx_error(VaultErrorKind::Parse, "KeePass database is damaged")
+}
+
+/// Times in KDBX 4 XML are base64 encoded seconds since year 1.
+pub fn encode_time(time: OffsetDateTime) -> String {
+    STANDARD.encode((time.unix_timestamp() + EPOCH_OFFSET).to_le_bytes())
+}
+
+pub fn decode_time(value: &str) -> Option<OffsetDateTime> {
+    let bytes: [u8; 8] = STANDARD.decode(value.trim()).ok()?.try_into().ok()?;
+    OffsetDateTime::from_unix_timestamp(i64::from_le_bytes(bytes).checked_sub(EPOCH_OFFSET)?).ok()
+}
+
+/// Keystream protected values are XORed with, in document order.
+pub struct InnerStream(ChaCha20);
+
+impl InnerStream {
+    fn new(key: &[u8]) -> Self {
+        let mut hash = Sha512::digest(key);
+        let cipher = ChaCha20::new_from_slices(&hash[..32], &hash[32..44]).expect("ChaCha20 key and nonce lengths are fixed");
+        hash.as_mut_slice().zeroize();
+
+        InnerStream(cipher)
+    }
+
+    pub fn decrypt(&mut self, value: &str) -> VaultResult<String> {
+        let mut bytes = STANDARD.decode(value.trim()).map_err(|_| corrupted())?;
+        self.0.apply_keystream(&mut bytes);
+
+        String::from_utf8(bytes).map_err(|_| corrupted())
+    }
+
+    pub fn encrypt(&mut self, value: &str) -> String {
+        let mut bytes = value.as_bytes().to_vec();
+        self.0.apply_keystream(&mut bytes);
+
+        STANDARD.encode(bytes)
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Key setup                                 */
+/* -------------------------------------------------------------------------- */
+/// The 32 byte key a key file stands for. XML key files hold it, 32 byte
+/// and 64 character hex files are it, anything else is hashed.
+fn key_file_key(data: &[u8]) -> VaultResult<[u8; 32]> {
+    if let Some(key) = xml_key_file(data)? {
+        return Ok(key);
+    }
+
+    if let Ok(key) = <[u8; 32]>::try_from(data) {
+        return Ok(key);
+    }
+
+    if data.len() == 64 {
+        if let Ok(key) = hex::decode(data) {
+            return Ok(key.try_into().expect("64 hex characters are 32 bytes"));
+        }
+    }
+
+    Ok(Sha256::digest(data).into())
+}
+
+fn xml_key_file(data: &[u8]) -> VaultResult<Option<[u8; 32]>> {
+    let Some(document) = std::str::from_utf8(data)
+        .ok()
+        .and_then(|t| roxmltree::Document::parse(t.trim_start_matches('\u{feff}')).ok())
+    else {
+        return Ok(None);
+    };
+    let root = document.root_element();
+    if !root.has_tag_name("KeyFile") {
+        return Ok(None);
+    }
+
+    fn find<'a, 'input>(parent: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+        parent.children().find(|n| n.has_tag_name(name))
+    }
+
+    let invalid = || kdbx_error(VaultErrorKind::Parse, "Key file is damaged");
+
+    let version = find(root, "Meta").and_then(|m| find(m, "Version")).and_then(|v| v.text()).unwrap_or("1.0");
+    let data = find(root, "Key").and_then(|k| find(k, "Data")).ok_or_else(invalid)?;
+    let text = data.text().unwrap_or_default();
+
+    let key = if version.starts_with('2') {
+        // Hex with a short hash to catch typos when typed in by hand
+        let key = hex::decode(text.split_whitespace().collect::<String>()).map_err(|_| invalid())?;
+        if let Some(hash) = data.attribute("Hash") {
+            if !hex::encode(&Sha256::digest(&key)[..4]).eq_ignore_ascii_case(hash) {
+                return Err(invalid());
+            }
+        }
+        key
+    } else {
+        STANDARD.decode(text.trim()).map_err(|_| invalid())?
+    };
+
+    key.try_into().map(Some).map_err(|_| invalid())
+}
+
+/// KeePass's composite key, the password and the key file each add a hash.
+pub fn composite_key(password: Option<&str>, key_file: Option<&Path>) -> VaultResult<[u8; 32]> {
+    let password = password.filter(|p| !p.is_empty());
+    if password.is_none() && key_file.is_none() {
+        return Err(kdbx_error(VaultErrorKind::Auth, "A password or key file is required"));
+    }
+
+    let mut hasher = Sha256::new();
+
+    if let Some(password) = password {
+        hasher.update(Sha256::digest(password.as_bytes()));
+    }
+
+    if let Some(path) = key_file {
+        let mut data = std::fs::read(path)
+            .map_err(|e| kdbx_error(VaultErrorKind::IO, format!("Couldn't read key file: {}", e)))?;
+        let key = key_file_key(&data);
+        data.zeroize();
+
+        let mut key = key?;
+        hasher.update(key);
+        key.zeroize();
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+/* -------------------------------------------------------------------------- */
+/*                             Variant dictionaries                           */
+/* -------------------------------------------------------------------------- */
+enum Variant {
+    UInt32(u32),
+    UInt64(u64),
+    Bytes(Vec<u8>),
+    // Booleans, signed integers and strings, none of which the KDFs need
+    Other,
+}
+
+const VARIANT_VERSION: u16 = 0x0100;
+const VARIANT_UINT32: u8 = 0x04;
+const VARIANT_UINT64: u8 = 0x05;
+const VARIANT_BYTES: u8 = 0x42;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> VaultResult<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n).ok_or_else(corrupted)?).ok_or_else(corrupted)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> VaultResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> VaultResult<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().expect("2 bytes")))
+    }
+
+    fn u32(&mut self) -> VaultResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    /// A header field, id and length prefixed.
+    fn field(&mut self) -> VaultResult<(u8, &'a [u8])> {
+        let id = self.u8()?;
+        let size = self.u32()? as usize;
+        Ok((id, self.take(size)?))
+    }
+}
+
+fn read_variants(data: &[u8]) -> VaultResult<HashMap<String, Variant>> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.u16()? >> 8 != VARIANT_VERSION >> 8 {
+        return Err(kdbx_error(VaultErrorKind::Parse, "Unsupported KDF parameter format"));
+    }
+
+    let mut variants = HashMap::new();
+
+    loop {
+        let kind = reader.u8()?;
+        if kind == 0 {
+            return Ok(variants);
+        }
+
+        let name_len = reader.u32()? as usize;
+        let name = String::from_utf8(reader.take(name_len)?.to_vec()).map_err(|_| corrupted())?;
+        let value_len = reader.u32()? as usize;
+        let value = reader.take(value_len)?;
+
+        let variant = match kind {
+            VARIANT_UINT32 => Variant::UInt32(u32::from_le_bytes(value.try_into().map_err(|_| corrupted())?)),
+            VARIANT_UINT64 => Variant::UInt64(u64::from_le_bytes(value.try_into().map_err(|_| corrupted())?)),
+            VARIANT_BYTES => Variant::Bytes(value.to_vec()),
+            _ => Variant::Other,
+        };
+
+        variants.insert(name, variant);
+    }
+}
+
+fn write_variants(variants: &[(&str, Variant)]) -> Vec<u8> {
+    let mut out = VARIANT_VERSION.to_le_bytes().to_vec();
+
+    for (name, variant) in variants {
+        let (kind, value) = match variant {
+            Variant::UInt32(v) => (VARIANT_UINT32, v.to_le_bytes().to_vec()),
+            Variant::UInt64(v) => (VARIANT_UINT64, v.to_le_bytes().to_vec()),
+            Variant::Bytes(v) => (VARIANT_BYTES, v.clone()),
+            Variant::Other => continue,
+        };
+
+        out.push(kind);
+        out.extend((name.len() as u32).to_le_bytes());
+        out.extend(name.as_bytes());
+        out.extend((value.len() as u32).to_le_bytes());
+        out.extend(value);
+    }
+
+    out.push(0);
+    out
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                Key derivation                              */
+/* -------------------------------------------------------------------------- */
+fn argon2(composite: &[u8; 32], salt: &[u8], algorithm: Algorithm, version: u32, m_cost: u64, t_cost: u64, p_cost: u32) -> VaultResult<[u8; 32]> {
+    let invalid = |e: String| kdbx_error(VaultErrorKind::Parse, format!("Invalid KDF parameters: {}", e));
+
+    let m_cost = u32::try_from(m_cost / 1024).map_err(|e| invalid(e.to_string()))?;
+    let t_cost = u32::try_from(t_cost).map_err(|e| invalid(e.to_string()))?;
+    check_argon2_costs(m_cost, t_cost, p_cost).map_err(invalid)?;
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32)).map_err(|e| invalid(e.to_string()))?;
+    let version = Version::try_from(version).map_err(|e| invalid(e.to_string()))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(algorithm, version, params)
+        .hash_password_into(composite, salt, &mut key)
+        .map_err(|e| kdbx_error(VaultErrorKind::Crypto, format!("Key derivation failed: {}", e)))?;
+
+    Ok(key)
+}
+
+/// Runs the KDF named in the header over the composite key.
+fn transform_key(composite: &[u8; 32], kdf: &HashMap<String, Variant>) -> VaultResult<[u8; 32]> {
+    let missing = |name: &str| kdbx_error(VaultErrorKind::Parse, format!("KDF parameter '{}' is missing", name));
+    let bytes = |name: &str| match kdf.get(name) {
+        Some(Variant::Bytes(v)) => Ok(v.as_slice()),
+        _ => Err(missing(name)),
+    };
+    let uint = |name: &str| match kdf.get(name) {
+        Some(Variant::UInt32(v)) => Ok(*v as u64),
+        Some(Variant::UInt64(v)) => Ok(*v),
+        _ => Err(missing(name)),
+    };
+
+    let id = bytes("$UUID")?;
+
+    if id == KDF_ARGON2D || id == KDF_ARGON2ID {
+        // Secret keys and associated data are part of the format but no client sets them
+        if kdf.contains_key("K") || kdf.contains_key("A") {
+            return Err(kdbx_error(VaultErrorKind::Parse, "Argon2 secret keys are not supported"));
+        }
+
+        let algorithm = if id == KDF_ARGON2D { Algorithm::Argon2d } else { Algorithm::Argon2id };
+        let version = uint("V").unwrap_or(0x13) as u32;
+        let parallelism = u32::try_from(uint("P")?).map_err(|_| corrupted())?;
+
+        return argon2(composite, bytes("S")?, algorithm, version, uint("M")?, uint("I")?, parallelism);
+    }
+
+    if id == KDF_AES || id == KDF_AES_KDBX4 {
+        let rounds = uint("R")?;
+        if rounds > AES_KDF_MAX_ROUNDS {
+            return Err(kdbx_error(VaultErrorKind::Parse, format!("Invalid KDF parameters: {} AES-KDF rounds", rounds)));
+        }
+
+        let cipher = Aes256::new_from_slice(bytes("S")?).map_err(|_| corrupted())?;
+        let mut blocks = [GenericArray::clone_from_slice(&composite[..16]), GenericArray::clone_from_slice(&composite[16..])];
+
+        for _ in 0..rounds {
+            cipher.encrypt_blocks(&mut blocks);
+        }
+
+        let key = Sha256::new().chain_update(blocks[0]).chain_update(blocks[1]).finalize().into();
+        blocks.iter_mut().for_each(|b| b.as_mut_slice().zeroize());
+
+        return Ok(key);
+    }
+
+    Err(kdbx_error(VaultErrorKind::Parse, "Unsupported key derivation function"))
+}
+
+struct Keys {
+    cipher: [u8; 32],
+    hmac: [u8; 64],
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        self.cipher.zeroize();
+        self.hmac.zeroize();
+    }
+}
+
+impl Keys {
+    fn new(master_seed: &[u8], mut transformed: [u8; 32]) -> Self {
+        let cipher = Sha256::new().chain_update(master_seed).chain_update(transformed).finalize().into();
+        let hmac = Sha512::new()
+            .chain_update(master_seed)
+            .chain_update(transformed)
+            .chain_update([1])
+            .finalize()
+            .into();
+        transformed.zeroize();
+
+        Keys { cipher, hmac }
+    }
+
+    /// Every block gets its own HMAC key, the header uses index `u64::MAX`.
+    fn block_hmac(&self, index: u64) -> Hmac<Sha256> {
+        let mut key = Sha512::new().chain_update(index.to_le_bytes()).chain_update(self.hmac).finalize();
+        let hmac = <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("HMAC accepts any key length");
+        key.as_mut_slice().zeroize();
+
+        hmac
+    }
+
+    fn block_mac(&self, index: u64, block: &[u8]) -> Hmac<Sha256> {
+        let mut hmac = self.block_hmac(index);
+        hmac.update(&index.to_le_bytes());
+        hmac.update(&(block.len() as u32).to_le_bytes());
+        hmac.update(block);
+
+        hmac
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Reading                                 */
+/* -------------------------------------------------------------------------- */
+/// Decrypts a KDBX 4 database and returns its XML along with the stream its
+/// protected values are encrypted with. Fails with an Auth error when the
+/// password or key file is wrong.
+pub fn open(data: &[u8], composite: &[u8; 32]) -> VaultResult<(String, InnerStream)> {
+    let mut reader = Reader { data, pos: 0 };
+
+    if reader.u32().ok() != Some(SIGNATURE_1) || reader.u32().ok() != Some(SIGNATURE_2) {
+        return Err(kdbx_error(VaultErrorKind::Parse, "Not a KeePass database"));
+    }
+
+    if reader.u32()? >> 16 != VERSION >> 16 {
+        return Err(kdbx_error(
+            VaultErrorKind::Parse,
+            "Only KDBX 4 databases are supported, save it with a current version of KeePass first",
+        ));
+    }
+
+    let mut header: HashMap<u8, &[u8]> = HashMap::new();
+    loop {
+        let (id, value) = reader.field()?;
+        if id == HEADER_END {
+            break;
+        }
+        header.insert(id, value);
+    }
+
+    let header_bytes = &data[..reader.pos];
+    if reader.take(32)? != Sha256::digest(header_bytes).as_slice() {
+        return Err(corrupted());
+    }
+    let header_mac = reader.take(32)?;
+
+    let field = |id: u8| header.get(&id).copied().ok_or_else(corrupted);
+    let keys = Keys::new(field(HEADER_MASTER_SEED)?, transform_key(composite, &read_variants(field(HEADER_KDF)?)?)?);
+
+    let mut hmac = keys.block_hmac(u64::MAX);
+    hmac.update(header_bytes);
+    hmac.verify_slice(header_mac)
+        .map_err(|_| kdbx_error(VaultErrorKind::Auth, "Wrong password or key file"))?;
+
+    // Authenticated blocks, ending with an empty one
+    let mut payload = Vec::new();
+    for index in 0u64.. {
+        let mac = reader.take(32)?;
+        let size = reader.u32()? as usize;
+        let block = reader.take(size)?;
+
+        keys.block_mac(index, block).verify_slice(mac).map_err(|_| corrupted())?;
+
+        if block.is_empty() {
+            break;
+        }
+        payload.extend_from_slice(block);
+    }
+
+    let iv = field(HEADER_IV)?;
+    let cipher = field(HEADER_CIPHER)?;
+    let mut plain = if cipher == CIPHER_CHACHA20 {
+        ChaCha20::new_from_slices(&keys.cipher, iv).map_err(|_| corrupted())?.apply_keystream(&mut payload);
+        payload
+    } else if cipher == CIPHER_AES256 {
+        cbc::Decryptor::<Aes256>::new_from_slices(&keys.cipher, iv)
+            .map_err(|_| corrupted())?
+            .decrypt_padded_vec_mut::<Pkcs7>(&payload)
+            .map_err(|_| corrupted())?
+    } else {
+        return Err(kdbx_error(VaultErrorKind::Parse, "Unsupported cipher, only AES and ChaCha20 databases can be opened"));
+    };
+
+    let compression = field(HEADER_COMPRESSION).ok().and_then(|c| c.try_into().ok()).map_or(0, u32::from_le_bytes);
+    if compression == COMPRESSION_GZIP {
+        let mut decompressed = Vec::new();
+        let result = GzDecoder::new(plain.as_slice()).read_to_end(&mut decompressed);
+        plain.zeroize();
+        result.map_err(|_| corrupted())?;
+        plain = decompressed;
+    }
+
+    let result = read_inner(&plain);
+    plain.zeroize();
+    result
+}
+
+fn read_inner(plain: &[u8]) -> VaultResult<(String, InnerStream)> {
+    let mut reader = Reader { data: plain, pos: 0 };
+    let mut stream_id = None;
+    let mut stream_key = None;
+
+    loop {
+        match reader.field()? {
+            (INNER_END, _) => break,
+            (INNER_STREAM_ID, value) => stream_id = value.try_into().ok().map(u32::from_le_bytes),
+            (INNER_STREAM_KEY, value) => stream_key = Some(value),
+            // Attachments aren't imported
+            _ => {}
+        }
+    }
+
+    if stream_id != Some(INNER_STREAM_CHACHA20) {
+        return Err(kdbx_error(VaultErrorKind::Parse, "Unsupported protected value encryption"));
+    }
+
+    let xml = std::str::from_utf8(&plain[reader.pos..]).map_err(|_| corrupted())?;
+
+    Ok((xml.to_string(), InnerStream::new(stream_key.ok_or_else(corrupted)?)))
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Writing                                 */
+/* -------------------------------------------------------------------------- */
+fn write_field(out: &mut Vec<u8>, id: u8, value: &[u8]) {
+    out.push(id);
+    out.extend((value.len() as u32).to_le_bytes());
+    out.extend(value);
+}
+
+/// Writes a KDBX 4 database encrypted with ChaCha20, its key derived with
+/// Argon2id and the cost settings of `kdf`. `xml` is handed the inner stream
+/// to encrypt protected values with and has to use it in document order.
+pub fn seal<F>(composite: &[u8; 32], kdf: &KdfParams, xml: F) -> VaultResult<Vec<u8>>
+where
+    F: FnOnce(&mut InnerStream) -> String,
+{
+    let mut master_seed = [0u8; 32];
+    let mut iv = [0u8; 12];
+    let mut salt = [0u8; 32];
+    let mut stream_key = [0u8; 64];
+    OsRng.fill_bytes(&mut master_seed);
+    OsRng.fill_bytes(&mut iv);
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut stream_key);
+
+    let kdf_parameters = write_variants(&[
+        ("$UUID", Variant::Bytes(KDF_ARGON2ID.to_vec())),
+        ("S", Variant::Bytes(salt.to_vec())),
+        ("P", Variant::UInt32(kdf.p_cost)),
+        ("M", Variant::UInt64(kdf.m_cost as u64 * 1024)),
+        ("I", Variant::UInt64(kdf.t_cost as u64)),
+        ("V", Variant::UInt32(0x13)),
+    ]);
+
+    let mut out = Vec::new();
+    out.extend(SIGNATURE_1.to_le_bytes());
+    out.extend(SIGNATURE_2.to_le_bytes());
+    out.extend(VERSION.to_le_bytes());
+    write_field(&mut out, HEADER_CIPHER, &CIPHER_CHACHA20);
+    write_field(&mut out, HEADER_COMPRESSION, &COMPRESSION_GZIP.to_le_bytes());
+    write_field(&mut out, HEADER_MASTER_SEED, &master_seed);
+    write_field(&mut out, HEADER_IV, &iv);
+    write_field(&mut out, HEADER_KDF, &kdf_parameters);
+    write_field(&mut out, HEADER_END, b"\r\n\r\n");
+
+    let keys = Keys::new(
+        &master_seed,
+        argon2(composite, &salt, Algorithm::Argon2id, 0x13, kdf.m_cost as u64 * 1024, kdf.t_cost as u64, kdf.p_cost)?,
+    );
+
+    let mut hmac = keys.block_hmac(u64::MAX);
+    hmac.update(&out);
+    let header_hash = Sha256::digest(&out);
+    out.extend(header_hash);
+    out.extend(hmac.finalize().into_bytes());
+
+    // Inner header, then the XML
+    let mut inner = Vec::new();
+    write_field(&mut inner, INNER_STREAM_ID, &INNER_STREAM_CHACHA20.to_le_bytes());
+    write_field(&mut inner, INNER_STREAM_KEY, &stream_key);
+    write_field(&mut inner, INNER_END, &[]);
+
+    let mut stream = InnerStream::new(&stream_key);
+    stream_key.zeroize();
+    let mut document = xml(&mut stream);
+    inner.extend(document.as_bytes());
+    document.zeroize();
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder.write_all(&inner).and_then(|_| encoder.finish());
+    inner.zeroize();
+
+    let mut payload = compressed.map_err(|e| kdbx_error(VaultErrorKind::IO, format!("Compression failed: {}", e)))?;
+    ChaCha20::new_from_slices(&keys.cipher, &iv)
+        .expect("ChaCha20 key and nonce lengths are fixed")
+        .apply_keystream(&mut payload);
+
+    let blocks = payload.chunks(BLOCK_SIZE).chain(std::iter::once(&[][..]));
+    for (index, block) in (0u64..).zip(blocks) {
+        out.extend(keys.block_mac(index, block).finalize().into_bytes());
+        out.extend((block.len() as u32).to_le_bytes());
+        out.extend(block);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_time_round_trips_and_rejects_out_of_range_values() {
+        let time = OffsetDateTime::from_unix_timestamp(1_714_521_600).unwrap();
+        assert_eq!(decode_time(&encode_time(time)), Some(time));
+
+        for seconds in [i64::MIN, i64::MAX] {
+            assert_eq!(decode_time(&STANDARD.encode(seconds.to_le_bytes())), None, "{}", seconds);
+        }
+    }
+}
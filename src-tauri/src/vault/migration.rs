@@ -0,0 +1,275 @@
+use crate::vault::crypto::KdfParams;
+use crate::vault::strength;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// A single upgrade step operating on raw JSON, so that older layouts
+/// don't need to deserialize into the current structs.
+pub type MigrationFn = fn(&mut Value) -> Result<(), String>;
+
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+
+    /// Runs on the outer `SaveFileLayout` right after the file is read.
+    pub layout: Option<MigrationFn>,
+
+    /// Runs on the decrypted entry payload after a successful unlock.
+    pub payload: Option<MigrationFn>,
+}
+
+/// Registered upgrade steps, ordered from oldest to newest.
+/// Every step must start at the `to` version of the previous one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: "1.0.1",
+        to: "1.1.0",
+        layout: Some(add_kdf_params),
+        payload: None,
+    },
+    Migration {
+        from: "1.1.0",
+        to: "1.2.0",
+        layout: Some(add_vault_id),
+        payload: None,
+    },
+    Migration {
+        from: "1.2.0",
+        to: "1.3.0",
+        layout: None,
+        payload: Some(wrap_entries),
+    },
+    Migration {
+        from: "1.3.0",
+        to: "1.4.0",
+        layout: None,
+        payload: Some(entries_to_items),
+    },
+    Migration {
+        from: "1.4.0",
+        to: "1.5.0",
+        layout: None,
+        payload: Some(rescore_passwords),
+    },
+];
+
+/// 1.1.0 stores the key derivation parameters, older vaults all used the Argon2 defaults.
+fn add_kdf_params(layout: &mut Value) -> Result<(), String> {
+    let crypto = layout
+        .get_mut("crypto")
+        .and_then(Value::as_object_mut)
+        .ok_or("missing crypto section")?;
+
+    let params = serde_json::to_value(KdfParams::legacy()).map_err(|e| e.to_string())?;
+    crypto.insert("kdf".into(), params);
+
+    Ok(())
+}
+
+/// 1.2.0 authenticates the header, which needs an id to tie it to this vault.
+/// The DEK wrap and payload get bound to it on the first save after unlock.
+fn add_vault_id(layout: &mut Value) -> Result<(), String> {
+    let crypto = layout
+        .get_mut("crypto")
+        .and_then(Value::as_object_mut)
+        .ok_or("missing crypto section")?;
+
+    crypto.insert("vault_id".into(), Value::String(Uuid::new_v4().to_string()));
+
+    Ok(())
+}
+
+/// 1.3.0 stores more than entries, so the payload became an object.
+fn wrap_entries(payload: &mut Value) -> Result<(), String> {
+    if !payload.is_array() {
+        return Err("entries are not a list".into());
+    }
+
+    let entries = payload.take();
+    *payload = serde_json::json!({ "entries": entries, "notes": [] });
+
+    Ok(())
+}
+
+/// 1.4.0 holds typed items, every existing entry becomes a login item.
+fn entries_to_items(payload: &mut Value) -> Result<(), String> {
+    let object = payload.as_object_mut().ok_or("payload is not an object")?;
+
+    let mut entries = match object.remove("entries") {
+        Some(Value::Array(entries)) => entries,
+        _ => return Err("entries are not a list".into()),
+    };
+
+    for entry in entries.iter_mut() {
+        entry
+            .as_object_mut()
+            .ok_or("entry is not an object")?
+            .insert("type".into(), Value::String("Login".into()));
+    }
+
+    object.insert("items".into(), Value::Array(entries));
+
+    Ok(())
+}
+
+/// 1.5.0 scores passwords on the backend, strengths sent by older clients are replaced.
+fn rescore_passwords(payload: &mut Value) -> Result<(), String> {
+    let items = payload
+        .get_mut("items")
+        .and_then(Value::as_array_mut)
+        .ok_or("items are not a list")?;
+
+    let rescore = |object: &mut Value| -> Result<(), String> {
+        let password = object.get("password").and_then(Value::as_str).ok_or("password missing")?;
+        let score = serde_json::to_value(strength::estimate(password)).map_err(|e| e.to_string())?;
+        object["passwordStrength"] = score;
+        Ok(())
+    };
+
+    for item in items.iter_mut().filter(|i| i["type"] == "Login") {
+        rescore(item)?;
+
+        if let Some(history) = item.get_mut("passwordHistory").and_then(Value::as_array_mut) {
+            for old in history.iter_mut() {
+                rescore(old)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.trim().split('.').map(|p| p.parse::<u32>().ok());
+
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some((major, minor, patch))
+}
+
+pub fn compare_versions(a: &str, b: &str) -> VaultResult<Ordering> {
+    let parse = |v: &str| {
+        parse_version(v).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::Version,
+            severity: VaultErrorSeverity::Fatal,
+            message: format!("Unrecognized vault version '{}'", v),
+            code: "E_LOAD_VERSION",
+        })
+    };
+
+    Ok(parse(a)?.cmp(&parse(b)?))
+}
+
+/// Collects the chain of steps needed to bring a vault saved with `from`
+/// up to `to`. Newer vaults and versions without a registered path are rejected.
+pub fn plan(from: &str, to: &str) -> VaultResult<Vec<&'static Migration>> {
+    match compare_versions(from, to)? {
+        Ordering::Equal => return Ok(Vec::new()),
+        Ordering::Greater => {
+            return Err(VaultError {
+                kind: VaultErrorKind::Version,
+                severity: VaultErrorSeverity::Fatal,
+                message: format!(
+                    "Vault was saved by a newer version of the application ({}). Downgrading to {} is not supported, please update the application.",
+                    from, to
+                ),
+                code: "E_LOAD_DOWNGRADE",
+            })
+        }
+        Ordering::Less => {}
+    }
+
+    let mut steps = Vec::new();
+    let mut current = from;
+
+    while current != to {
+        let step = MIGRATIONS
+            .iter()
+            .find(|m| m.from == current)
+            .ok_or_else(|| VaultError {
+                kind: VaultErrorKind::Version,
+                severity: VaultErrorSeverity::Fatal,
+                message: format!(
+                    "No migration path from vault version {} to {}",
+                    from, to
+                ),
+                code: "E_LOAD_VERSION",
+            })?;
+
+        steps.push(step);
+        current = step.to;
+    }
+
+    Ok(steps)
+}
+
+/// Upgrades the raw save file in place and stamps the new version on it.
+pub fn migrate_layout(steps: &[&Migration], layout: &mut Value) -> VaultResult<()> {
+    for step in steps {
+        if let Some(migrate) = step.layout {
+            migrate(layout).map_err(|e| VaultError {
+                kind: VaultErrorKind::Parse,
+                severity: VaultErrorSeverity::Fatal,
+                message: format!(
+                    "Failed to migrate vault from {} to {}: {}",
+                    step.from, step.to, e
+                ),
+                code: "E_MIGRATE_LAYOUT",
+            })?;
+        }
+
+        layout["version"] = Value::String(step.to.into());
+    }
+
+    Ok(())
+}
+
+/// Upgrades the decrypted entry payload in place.
+pub fn migrate_payload(steps: &[&Migration], payload: &mut Value) -> VaultResult<()> {
+    for step in steps {
+        if let Some(migrate) = step.payload {
+            migrate(payload).map_err(|e| VaultError {
+                kind: VaultErrorKind::Parse,
+                severity: VaultErrorSeverity::Blocking,
+                message: format!(
+                    "Failed to migrate vault entries from {} to {}: {}",
+                    step.from, step.to, e
+                ),
+                code: "E_MIGRATE_PAYLOAD",
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the untouched vault file next to itself before anything gets upgraded.
+/// An existing backup for the same version is never overwritten.
+pub fn write_backup(path: &Path, version: &str) -> VaultResult<PathBuf> {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "vault".into());
+
+    let backup_path = path.with_file_name(format!("{}.v{}.premigration.json", stem, version));
+
+    if !backup_path.exists() {
+        std::fs::copy(path, &backup_path).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Fatal,
+            message: format!("Couldn't write pre-migration backup: {}", e),
+            code: "E_MIGRATE_BACKUP",
+        })?;
+    }
+
+    Ok(backup_path)
+}
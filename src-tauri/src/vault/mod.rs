@@ -1,5 +1,23 @@
+pub mod audit;
+pub mod autolock;
+pub mod backup;
 pub mod crypto;
 pub mod entry;
+pub mod export;
+pub mod folder;
+pub mod generator;
+pub mod import;
+pub mod item;
+pub mod kdbx;
+pub mod migration;
+pub mod note;
+pub mod search;
+pub mod storage;
+pub mod strength;
+pub mod throttle;
+pub mod totp;
+pub mod trash;
+pub mod url_match;
 pub mod vault;
 
 pub use crypto::VaultCryptoManager;
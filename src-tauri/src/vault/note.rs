@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Standalone item holding free-form text, without any login details.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SecureNote {
+    pub id: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub modified_at: OffsetDateTime,
+    pub label: String,
+    pub content: String,
+    pub favorite: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SecureNotePublic {
+    pub id: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub modified_at: OffsetDateTime,
+    pub label: String,
+    pub favorite: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSecureNote {
+    pub label: Option<String>,
+    pub content: Option<String>,
+    pub favorite: Option<bool>,
+}
+
+impl From<&SecureNote> for SecureNotePublic {
+    fn from(note: &SecureNote) -> Self {
+        SecureNotePublic {
+            id: note.id,
+            created_at: note.created_at,
+            modified_at: note.modified_at,
+            label: note.label.clone(),
+            favorite: note.favorite,
+        }
+    }
+}
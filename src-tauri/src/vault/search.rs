@@ -0,0 +1,262 @@
+use crate::vault::entry::{Entry, EntryPublic};
+use crate::vault::url_match;
+use serde::Serialize;
+use std::collections::HashSet;
+use time::OffsetDateTime;
+
+// How much a match in each field is worth relative to the label
+const LABEL_WEIGHT: f64 = 1.0;
+const HOST_WEIGHT: f64 = 0.8;
+const NAME_WEIGHT: f64 = 0.7;
+const TAG_WEIGHT: f64 = 0.6;
+
+// Per matched character
+const MATCH_SCORE: f64 = 16.0;
+const CONSECUTIVE_BONUS: f64 = 12.0;
+const WORD_START_BONUS: f64 = 10.0;
+const PREFIX_BONUS: f64 = 20.0;
+const GAP_PENALTY: f64 = 1.5;
+
+// Usage signals only reorder results of a similar match quality
+const USES_WEIGHT: f64 = 4.0;
+const RECENCY_WEIGHT: f64 = 12.0;
+const RECENCY_HALF_LIFE_DAYS: f64 = 14.0;
+const FAVORITE_BONUS: f64 = 10.0;
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub enum SearchField {
+    Label,
+    Name,
+    Host,
+    Tag,
+}
+
+/// Half-open range into the field value, in UTF-16 code units so the
+/// frontend can slice JavaScript strings with it directly.
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMatch {
+    pub field: SearchField,
+    pub value: String,
+    pub ranges: Vec<MatchRange>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub entry: EntryPublic,
+    pub score: f64,
+    pub matches: Vec<FieldMatch>,
+}
+
+struct FuzzyMatch {
+    score: f64,
+    ranges: Vec<MatchRange>,
+}
+
+/// Host part of an entry URL, which is often stored without a scheme.
+pub fn url_host(url: &str) -> Option<String> {
+    url_match::parse(url)?
+        .host_str()
+        .map(|h| h.trim_start_matches("www.").to_string())
+}
+
+fn is_separator(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+/// Scores `pattern` as a subsequence of `text`, case-insensitively. Every
+/// occurrence of the first character is tried as a starting point and the
+/// best scoring alignment wins.
+fn fuzzy_match(pattern: &[char], text: &str) -> Option<FuzzyMatch> {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let first = *pattern.first()?;
+    let mut best: Option<(f64, Vec<usize>)> = None;
+
+    for start in (0..lower.len()).filter(|i| lower[*i] == first) {
+        let mut positions = Vec::with_capacity(pattern.len());
+        let mut i = start;
+
+        for p in pattern {
+            while i < lower.len() && lower[i] != *p {
+                i += 1;
+            }
+            if i == lower.len() {
+                break;
+            }
+            positions.push(i);
+            i += 1;
+        }
+
+        if positions.len() < pattern.len() {
+            // Later starts only have less text left to match in
+            break;
+        }
+
+        let score = score_positions(&positions, &chars);
+        if best.as_ref().is_none_or(|(b, _)| score > *b) {
+            best = Some((score, positions));
+        }
+    }
+
+    let (score, positions) = best?;
+
+    Some(FuzzyMatch {
+        score,
+        ranges: to_ranges(&positions, &chars),
+    })
+}
+
+fn score_positions(positions: &[usize], chars: &[char]) -> f64 {
+    let mut score = 0.0;
+
+    for (n, pos) in positions.iter().enumerate() {
+        score += MATCH_SCORE;
+
+        if *pos == 0 {
+            score += PREFIX_BONUS;
+        } else if is_separator(chars[pos - 1]) || (chars[pos - 1].is_lowercase() && chars[*pos].is_uppercase()) {
+            score += WORD_START_BONUS;
+        }
+
+        if n > 0 {
+            let gap = pos - positions[n - 1] - 1;
+            if gap == 0 {
+                score += CONSECUTIVE_BONUS;
+            } else {
+                score -= GAP_PENALTY * gap as f64;
+            }
+        }
+    }
+
+    // Shorter values that match the same characters are better matches
+    score - (chars.len() - positions.len()) as f64 * 0.1
+}
+
+/// Merges matched character positions into UTF-16 ranges.
+fn to_ranges(positions: &[usize], chars: &[char]) -> Vec<MatchRange> {
+    let mut offsets = Vec::with_capacity(chars.len() + 1);
+    let mut offset = 0;
+    for c in chars {
+        offsets.push(offset);
+        offset += c.len_utf16();
+    }
+    offsets.push(offset);
+
+    let mut ranges: Vec<MatchRange> = Vec::new();
+    for pos in positions {
+        let (start, end) = (offsets[*pos], offsets[pos + 1]);
+
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => ranges.push(MatchRange { start, end }),
+        }
+    }
+
+    ranges
+}
+
+fn usage_score(entry: &Entry, now: OffsetDateTime) -> f64 {
+    let mut score = USES_WEIGHT * (1.0 + entry.uses as f64).ln();
+
+    if let Some(last_used) = entry.last_used {
+        let days = (now - last_used).as_seconds_f64().max(0.0) / 86_400.0;
+        score += RECENCY_WEIGHT * 0.5f64.powf(days / RECENCY_HALF_LIFE_DAYS);
+    }
+
+    if entry.favorite {
+        score += FAVORITE_BONUS;
+    }
+
+    score
+}
+
+/// Adds the ranges of a term match to the field it was found in.
+fn add_match(matches: &mut Vec<FieldMatch>, field: SearchField, value: &str, ranges: Vec<MatchRange>) {
+    match matches.iter_mut().find(|m| m.field == field && m.value == value) {
+        Some(existing) => {
+            existing.ranges.extend(ranges);
+            existing.ranges.sort_by_key(|r| r.start);
+        }
+        None => matches.push(FieldMatch {
+            field,
+            value: value.to_string(),
+            ranges,
+        }),
+    }
+}
+
+/// Score and matched fields of an entry, `None` unless every term matches.
+fn search_entry(entry: &Entry, terms: &[Vec<char>], now: OffsetDateTime) -> Option<(f64, Vec<FieldMatch>)> {
+    // Several URLs often share a host, each host is only searched once
+    let mut seen = HashSet::new();
+    let mut hosts: Vec<String> = entry.all_urls().filter_map(|u| url_host(u)).collect();
+    hosts.retain(|h| seen.insert(h.clone()));
+
+    let mut fields = vec![
+        (SearchField::Label, entry.label.as_str(), LABEL_WEIGHT),
+        (SearchField::Name, entry.name.as_str(), NAME_WEIGHT),
+    ];
+    fields.extend(hosts.iter().map(|h| (SearchField::Host, h.as_str(), HOST_WEIGHT)));
+    fields.extend(entry.tags.iter().map(|t| (SearchField::Tag, t.as_str(), TAG_WEIGHT)));
+
+    let mut score = 0.0;
+    let mut matches = Vec::new();
+
+    // Every term has to match somewhere, each one counts with its best field
+    for term in terms {
+        let (field, value, weight, found) = fields
+            .iter()
+            .filter_map(|(field, value, weight)| {
+                fuzzy_match(term, value).map(|m| (*field, *value, *weight, m))
+            })
+            .max_by(|a, b| (a.3.score * a.2).total_cmp(&(b.3.score * b.2)))?;
+
+        score += found.score * weight;
+        add_match(&mut matches, field, value, found.ranges);
+    }
+
+    Some((score + usage_score(entry, now), matches))
+}
+
+/// Fuzzy searches entries by label, username, URL host and tags, best matches first.
+pub fn search<'a>(entries: impl Iterator<Item = &'a Entry>, query: &str, limit: usize) -> Vec<SearchResult> {
+    let terms: Vec<Vec<char>> = query
+        .split_whitespace()
+        .map(|t| t.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect())
+        .collect();
+
+    if terms.is_empty() || limit == 0 {
+        return Vec::new();
+    }
+
+    let now = OffsetDateTime::now_utc();
+
+    let mut results: Vec<(&Entry, f64, Vec<FieldMatch>)> = entries
+        .filter_map(|e| search_entry(e, &terms, now).map(|(score, matches)| (e, score, matches)))
+        .collect();
+
+    results.sort_by(|a, b| b.1.total_cmp(&a.1));
+    results.truncate(limit);
+
+    // Only the returned entries get projected
+    results
+        .into_iter()
+        .map(|(entry, score, matches)| SearchResult {
+            entry: EntryPublic::from(entry),
+            score,
+            matches,
+        })
+        .collect()
+}
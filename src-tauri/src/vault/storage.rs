@@ -0,0 +1,84 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Sibling file every save is staged in before it replaces the real one.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Crash-safe replacement of `path`: write to a temp file, fsync it,
+/// rename it over the target and fsync the directory so the rename sticks.
+/// At any point the target holds either the old or the new content, never a mix.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp = temp_path(path);
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp)?;
+
+        file.write_all(content)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&tmp, path)?;
+        sync_parent_dir(path)
+    })();
+
+    // Don't leave a half-written temp file behind if we can help it
+    if result.is_err() && tmp.exists() {
+        let _ = fs::remove_file(&tmp);
+    }
+
+    result
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::File::open(dir)?.sync_all(),
+        _ => Ok(()),
+    }
+}
+
+// Directories can't be opened for syncing on Windows, rename is durable there once it returns
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn write_atomic_replaces_content_without_leftovers() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert!(!temp_path(&path).exists());
+    }
+
+    #[test]
+    fn failed_write_removes_temp_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+
+        // Renaming a file over a directory fails after the temp file was written
+        fs::create_dir(&path).unwrap();
+
+        assert!(write_atomic(&path, b"new").is_err());
+        assert!(path.is_dir());
+        assert!(!temp_path(&path).exists());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::vault::entry::PasswordStrength;
+use crate::vault::generator::WORDLIST;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// Most common first, same list the frontend uses
+const COMMON_PASSWORDS: &str = include_str!("../../data/common_passwords.txt");
+
+const KEYBOARD_ROWS: [&str; 4] = ["`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"];
+
+// Entropy in bits needed for each strength level
+const OKAY_BITS: f64 = 32.0;
+const STRONG_BITS: f64 = 48.0;
+const EXCELLENT_BITS: f64 = 64.0;
+
+const MIN_WORD_LENGTH: usize = 3;
+const MAX_WORD_LENGTH: usize = 16;
+
+static DICTIONARY: OnceLock<HashMap<String, f64>> = OnceLock::new();
+
+/// Guessing cost of every known word. Common passwords cost their rank,
+/// words from the passphrase list are all equally likely.
+fn dictionary() -> &'static HashMap<String, f64> {
+    DICTIONARY.get_or_init(|| {
+        let word_bits = (WORDLIST.lines().count() as f64).log2();
+        let mut words: HashMap<String, f64> = WORDLIST.lines().map(|w| (w.trim().to_string(), word_bits)).collect();
+
+        for (rank, password) in COMMON_PASSWORDS.lines().enumerate() {
+            words.insert(password.trim().to_string(), ((rank + 2) as f64).log2());
+        }
+
+        words.remove("");
+        words
+    })
+}
+
+/// Part of the password explained by a pattern, `end` is exclusive.
+struct Match {
+    start: usize,
+    end: usize,
+    bits: f64,
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '5' | '$' => 's',
+        '7' => 't',
+        _ => c,
+    }
+}
+
+fn dictionary_matches(chars: &[char], matches: &mut Vec<Match>) {
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+
+    for start in 0..lower.len() {
+        for end in (start + MIN_WORD_LENGTH)..=(start + MAX_WORD_LENGTH).min(lower.len()) {
+            let plain: String = lower[start..end].iter().collect();
+            let leet: String = plain.chars().map(unleet).collect();
+            let reversed: String = plain.chars().rev().collect();
+
+            // Each variation the attacker has to try costs about a bit
+            let candidates = [(&plain, 0.0), (&leet, 1.0), (&reversed, 1.0)];
+            let Some(bits) = candidates
+                .iter()
+                .filter_map(|(word, extra)| dictionary().get(*word).map(|bits| bits + extra))
+                .min_by(f64::total_cmp)
+            else {
+                continue;
+            };
+
+            let capitalized = chars[start..end].iter().any(|c| c.is_uppercase());
+            matches.push(Match {
+                start,
+                end,
+                bits: bits + if capitalized { 1.0 } else { 0.0 },
+            });
+        }
+    }
+}
+
+fn keyboard_position(c: char) -> Option<(usize, usize)> {
+    KEYBOARD_ROWS
+        .iter()
+        .enumerate()
+        .find_map(|(row, keys)| keys.chars().position(|k| k == c).map(|col| (row, col)))
+}
+
+/// Runs of neighbouring keys like `qwerty` or `lkjh`.
+fn keyboard_matches(chars: &[char], matches: &mut Vec<Match>) {
+    let keys: Vec<usize> = KEYBOARD_ROWS.iter().map(|r| r.len()).collect();
+    let positions: Vec<Option<(usize, usize)>> = chars
+        .iter()
+        .map(|c| keyboard_position(c.to_lowercase().next().unwrap_or(*c)))
+        .collect();
+
+    let adjacent = |i: usize| match (positions[i - 1], positions[i]) {
+        (Some((r1, c1)), Some((r2, c2))) => r1 == r2 && c1.abs_diff(c2) == 1,
+        _ => false,
+    };
+
+    walks(chars.len(), adjacent, |start, end| {
+        matches.push(Match {
+            start,
+            end,
+            // Starting key, direction and length
+            bits: (keys.iter().sum::<usize>() as f64).log2() + 1.0 + ((end - start) as f64).log2(),
+        })
+    });
+}
+
+/// Alphabetic or numeric runs like `abcd` or `9876`.
+fn sequence_matches(chars: &[char], matches: &mut Vec<Match>) {
+    for direction in [1, -1] {
+        let step = |i: usize| {
+            chars[i].is_alphanumeric()
+                && chars[i - 1].is_alphanumeric()
+                && chars[i] as i64 - chars[i - 1] as i64 == direction
+        };
+
+        walks(chars.len(), step, |start, end| {
+            let space: f64 = if chars[start].is_ascii_digit() { 10.0 } else { 26.0 };
+            matches.push(Match {
+                start,
+                end,
+                bits: space.log2() + 1.0 + ((end - start) as f64).log2(),
+            })
+        });
+    }
+}
+
+/// Calls `found` for every maximal run of at least three characters where
+/// `continues(i)` holds between character `i - 1` and `i`.
+fn walks(len: usize, continues: impl Fn(usize) -> bool, mut found: impl FnMut(usize, usize)) {
+    let mut start = 0;
+
+    for i in 1..=len {
+        if i == len || !continues(i) {
+            if i - start >= 3 {
+                found(start, i);
+            }
+            start = i;
+        }
+    }
+}
+
+/// The same block repeated back to back, like `aaaa` or `abcabc`. Takes the
+/// longest repeat at each position and continues after it, so the blocks
+/// only get scored once.
+fn repeat_matches(chars: &[char], matches: &mut Vec<Match>) {
+    let mut start = 0;
+
+    while start < chars.len() {
+        let repeat = (1..=(chars.len() - start) / 2)
+            .map(|block| {
+                let pattern = &chars[start..start + block];
+                let mut count = 1;
+
+                while start + (count + 1) * block <= chars.len()
+                    && chars[start + count * block..start + (count + 1) * block] == *pattern
+                {
+                    count += 1;
+                }
+
+                (block, count)
+            })
+            .filter(|(block, count)| *count > 1 && block * count >= 3)
+            // Most characters covered, by the shortest block
+            .min_by_key(|(block, count)| (std::cmp::Reverse(block * count), *block));
+
+        let Some((block, count)) = repeat else {
+            start += 1;
+            continue;
+        };
+
+        let text: String = chars[start..start + block].iter().collect();
+        matches.push(Match {
+            start,
+            end: start + block * count,
+            bits: entropy(&text) + (count as f64).log2(),
+        });
+
+        start += block * count;
+    }
+}
+
+fn valid_date(day: u32, month: u32) -> bool {
+    (1..=12).contains(&month) && (1..=31).contains(&day)
+}
+
+/// Years and numeric dates, with or without separators.
+fn date_matches(chars: &[char], matches: &mut Vec<Match>) {
+    for start in 0..chars.len() {
+        for end in (start + 4)..=(start + 10).min(chars.len()) {
+            let part = &chars[start..end];
+            let separators: Vec<char> = part.iter().filter(|c| !c.is_ascii_digit()).copied().collect();
+
+            // Either no separators or two of the same kind
+            let separated = match separators.as_slice() {
+                [] => false,
+                [a, b] if a == b && "/-._ ".contains(*a) => true,
+                _ => continue,
+            };
+
+            let digits: String = part.iter().filter(|c| c.is_ascii_digit()).collect();
+            let number = |range: std::ops::Range<usize>| digits[range].parse::<u32>().unwrap_or(0);
+
+            let is_date = match digits.len() {
+                4 if !separated => (1900..=2099).contains(&number(0..4)),
+                6 => valid_date(number(0..2), number(2..4))
+                    || valid_date(number(2..4), number(0..2))
+                    || valid_date(number(4..6), number(2..4)),
+                8 => {
+                    ((1900..=2099).contains(&number(4..8))
+                        && (valid_date(number(0..2), number(2..4)) || valid_date(number(2..4), number(0..2))))
+                        || ((1900..=2099).contains(&number(0..4)) && valid_date(number(6..8), number(4..6)))
+                }
+                _ => false,
+            };
+
+            if is_date {
+                matches.push(Match {
+                    start,
+                    end,
+                    // A year alone, or a day within about two centuries
+                    bits: if digits.len() == 4 { 200f64.log2() } else { (365.0 * 200.0f64).log2() },
+                });
+            }
+        }
+    }
+}
+
+/// Size of the character classes in use, like the frontend estimate.
+fn charset_size(chars: &[char]) -> f64 {
+    let mut size = 0.0;
+
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        size += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        size += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        size += 10.0;
+    }
+    if chars.iter().any(|c| !c.is_ascii_alphanumeric()) {
+        size += 33.0;
+    }
+
+    size
+}
+
+/// Estimated guessing entropy in bits. The password is split into the
+/// cheapest combination of known patterns and random characters.
+pub fn entropy(password: &str) -> f64 {
+    let chars: Vec<char> = password.chars().collect();
+    if chars.is_empty() {
+        return 0.0;
+    }
+
+    let mut matches = Vec::new();
+    dictionary_matches(&chars, &mut matches);
+    keyboard_matches(&chars, &mut matches);
+    sequence_matches(&chars, &mut matches);
+    repeat_matches(&chars, &mut matches);
+    date_matches(&chars, &mut matches);
+
+    let per_char = charset_size(&chars).log2();
+
+    // best[i] is the cheapest way to produce the first i characters
+    let mut best = vec![f64::INFINITY; chars.len() + 1];
+    best[0] = 0.0;
+
+    for i in 1..=chars.len() {
+        best[i] = best[i - 1] + per_char;
+
+        for m in matches.iter().filter(|m| m.end == i) {
+            best[i] = best[i].min(best[m.start] + m.bits);
+        }
+    }
+
+    best[chars.len()]
+}
+
+pub fn estimate(password: &str) -> PasswordStrength {
+    let normalized = password.trim().to_lowercase();
+
+    if normalized.is_empty() || COMMON_PASSWORDS.lines().any(|p| p == normalized) {
+        return PasswordStrength::Weak;
+    }
+
+    match entropy(password) {
+        bits if bits < OKAY_BITS => PasswordStrength::Weak,
+        bits if bits < STRONG_BITS => PasswordStrength::Okay,
+        bits if bits < EXCELLENT_BITS => PasswordStrength::Strong,
+        _ => PasswordStrength::Excellent,
+    }
+}
@@ -0,0 +1,123 @@
+use crate::vault::storage;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use time::{Duration, OffsetDateTime};
+
+const ATTEMPTS_FILE: &str = "unlock_attempts.json";
+
+// Wrong passwords allowed before any delay kicks in
+const FREE_ATTEMPTS: u32 = 3;
+const BASE_DELAY_SECS: i64 = 2;
+const MAX_DELAY_SECS: i64 = 15 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct AttemptRecord {
+    failed_attempts: u32,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    last_failure: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ThrottleStatus {
+    pub failed_attempts: u32,
+    pub retry_after_secs: u64,
+}
+
+/// Exponential backoff between failed password attempts. The counter is
+/// stored next to the vault file so restarting the app doesn't reset it.
+#[derive(Default)]
+pub struct UnlockThrottle {
+    path: PathBuf,
+    record: AttemptRecord,
+}
+
+impl UnlockThrottle {
+    pub fn set_vault_path(&mut self, vault_path: &Path) {
+        self.path = vault_path.with_file_name(ATTEMPTS_FILE);
+
+        self.record = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+    }
+
+    fn delay(&self) -> Duration {
+        if self.record.failed_attempts <= FREE_ATTEMPTS {
+            return Duration::ZERO;
+        }
+
+        let exponent = (self.record.failed_attempts - FREE_ATTEMPTS - 1).min(20);
+        Duration::seconds((BASE_DELAY_SECS << exponent).min(MAX_DELAY_SECS))
+    }
+
+    fn remaining(&self) -> Duration {
+        match self.record.last_failure {
+            Some(last) => (last + self.delay() - OffsetDateTime::now_utc()).max(Duration::ZERO),
+            None => Duration::ZERO,
+        }
+    }
+
+    pub fn get_status(&self) -> ThrottleStatus {
+        ThrottleStatus {
+            failed_attempts: self.record.failed_attempts,
+            retry_after_secs: self.remaining().whole_seconds().max(0) as u64,
+        }
+    }
+
+    /// Fails with a Lockout error while the backoff from earlier failures is running.
+    pub fn check(&self) -> VaultResult<()> {
+        let remaining = self.remaining();
+
+        if remaining.is_positive() {
+            // Round up so the client never retries a moment too early
+            let secs = (remaining.whole_milliseconds() as i64 + 999) / 1000;
+
+            return Err(VaultError {
+                kind: VaultErrorKind::Lockout,
+                severity: VaultErrorSeverity::Soft,
+                message: format!(
+                    "Too many failed attempts. Try again in {} second{}.",
+                    secs,
+                    if secs == 1 { "" } else { "s" }
+                ),
+                code: "E_UNLOCK_LOCKOUT",
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn record_failure(&mut self) -> VaultResult<()> {
+        self.record.failed_attempts = self.record.failed_attempts.saturating_add(1);
+        self.record.last_failure = Some(OffsetDateTime::now_utc());
+        self.persist()
+    }
+
+    pub fn reset(&mut self) -> VaultResult<()> {
+        if self.record.failed_attempts == 0 {
+            return Ok(());
+        }
+
+        self.record = AttemptRecord::default();
+        self.persist()
+    }
+
+    fn persist(&self) -> VaultResult<()> {
+        let content = serde_json::to_string_pretty(&self.record).map_err(|e| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Blocking,
+            message: format!("Serialization failed: {e}"),
+            code: "E_UNLOCK_THROTTLE",
+        })?;
+
+        storage::write_atomic(&self.path, content.as_bytes()).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Blocking,
+            message: format!("Couldn't save failed attempt counter: {e}"),
+            code: "E_UNLOCK_THROTTLE",
+        })
+    }
+}
@@ -0,0 +1,67 @@
+use crate::vault::storage;
+use crate::vault::vault::{VaultError, VaultErrorKind, VaultErrorSeverity, VaultResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use time::{Duration, OffsetDateTime};
+
+const SETTINGS_FILE: &str = "trash.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashSettings {
+    /// Days a deleted entry stays restorable, 0 keeps it until purged by hand.
+    pub retention_days: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        TrashSettings { retention_days: 30 }
+    }
+}
+
+/// Retention settings of the trash, kept next to the vault file.
+#[derive(Default)]
+pub struct Trash {
+    path: PathBuf,
+    settings: TrashSettings,
+}
+
+impl Trash {
+    pub fn set_vault_path(&mut self, vault_path: &Path) {
+        self.path = vault_path.with_file_name(SETTINGS_FILE);
+
+        self.settings = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn get_settings(&self) -> TrashSettings {
+        self.settings.clone()
+    }
+
+    pub fn set_settings(&mut self, settings: TrashSettings) -> VaultResult<()> {
+        let content = serde_json::to_string_pretty(&settings).map_err(|e| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Serialization failed: {e}"),
+            code: "E_TRASH_SETTINGS",
+        })?;
+
+        storage::write_atomic(&self.path, content.as_bytes()).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Couldn't save trash settings: {e}"),
+            code: "E_TRASH_SETTINGS",
+        })?;
+
+        self.settings = settings;
+
+        Ok(())
+    }
+
+    pub fn is_expired(&self, deleted_at: OffsetDateTime) -> bool {
+        self.settings.retention_days > 0
+            && OffsetDateTime::now_utc() - deleted_at >= Duration::days(self.settings.retention_days.into())
+    }
+}
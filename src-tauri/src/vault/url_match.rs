@@ -0,0 +1,117 @@
+use crate::vault::entry::{Entry, UrlMatchMode};
+use publicsuffix::{List, Psl};
+use regex::RegexBuilder;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use url::{Host, Url};
+
+// Snapshot of https://publicsuffix.org/list/public_suffix_list.dat
+const SUFFIX_LIST: &str = include_str!("../../data/public_suffix_list.dat");
+
+static LIST: OnceLock<List> = OnceLock::new();
+
+fn suffix_list() -> &'static List {
+    LIST.get_or_init(|| SUFFIX_LIST.parse().unwrap_or_default())
+}
+
+/// Parses a URL the way users type them, so `example.com/login` and
+/// `localhost:8080` are read as https URLs instead of failing or being
+/// taken for a scheme. The host comes back lowercased and punycoded.
+pub fn parse(url: &str) -> Option<Url> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+
+    let mut parsed = Url::parse(url)
+        .ok()
+        .filter(|u| u.has_host())
+        .or_else(|| Url::parse(&format!("https://{}", url)).ok())?;
+
+    // `example.com.` and `example.com` are the same site
+    if let Some(host) = parsed.host_str().filter(|h| h.len() > 1 && h.ends_with('.')) {
+        let host = host.trim_end_matches('.').to_string();
+        parsed.set_host(Some(&host)).ok()?;
+    }
+
+    Some(parsed)
+}
+
+/// Registrable domain of a host, `login.example.co.uk` gives `example.co.uk`.
+/// IP addresses and single label hosts like `localhost` are returned as is.
+pub fn base_domain(host: &str) -> String {
+    if host.parse::<IpAddr>().is_ok() || host.starts_with('[') {
+        return host.to_string();
+    }
+
+    suffix_list()
+        .domain(host.as_bytes())
+        .and_then(|d| std::str::from_utf8(d.as_bytes()).ok().map(str::to_string))
+        .unwrap_or_else(|| host.to_string())
+}
+
+/// Host and port as written, the scheme doesn't count. This is Bitwarden's
+/// host rule, which imports and exports map to.
+fn same_host(a: &Url, b: &Url) -> bool {
+    a.host() == b.host() && a.port() == b.port()
+}
+
+/// How specific a match of `pattern` against `target` is, `None` when it
+/// doesn't match at all. Exact host and path matches rank above matches
+/// on the base domain only.
+pub fn match_url(mode: UrlMatchMode, pattern: &str, target: &Url) -> Option<u8> {
+    match mode {
+        UrlMatchMode::Never => None,
+        UrlMatchMode::RegEx => {
+            let regex = RegexBuilder::new(pattern.trim()).case_insensitive(true).build().ok()?;
+            regex.is_match(target.as_str()).then_some(3)
+        }
+        UrlMatchMode::StartsWith => {
+            let pattern = parse(pattern)?;
+            target.as_str().starts_with(pattern.as_str()).then_some(3)
+        }
+        UrlMatchMode::Host => {
+            let pattern = parse(pattern)?;
+            same_host(&pattern, target).then_some(2)
+        }
+        UrlMatchMode::BaseDomain => {
+            let pattern = parse(pattern)?;
+            if pattern.host() == target.host() {
+                return Some(2);
+            }
+
+            match (pattern.host(), target.host()) {
+                (Some(Host::Domain(a)), Some(Host::Domain(b))) => (base_domain(a) == base_domain(b)).then_some(1),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Best match of any of the entry's URLs against `target`.
+pub fn match_entry(entry: &Entry, target: &Url) -> Option<u8> {
+    entry.all_urls().filter_map(|u| match_url(entry.url_match, u, target)).max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host_match(pattern: &str, target: &str) -> bool {
+        match_url(UrlMatchMode::Host, pattern, &parse(target).unwrap()).is_some()
+    }
+
+    #[test]
+    fn host_mode_ignores_scheme() {
+        assert!(host_match("http://example.com", "https://example.com/login"));
+        assert!(host_match("https://example.com", "http://example.com"));
+        assert!(!host_match("https://example.com", "https://www.example.com"));
+    }
+
+    #[test]
+    fn host_mode_compares_explicit_ports_only() {
+        assert!(host_match("example.com:8443", "https://example.com:8443/admin"));
+        assert!(!host_match("example.com:8443", "https://example.com"));
+        assert!(!host_match("http://example.com", "https://example.com:8080"));
+    }
+}
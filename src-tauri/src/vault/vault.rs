@@ -1,17 +1,39 @@
-use crate::vault::entry::{EntryPublic, UpdateEntry};
+use crate::vault::audit::{self, SecurityReport};
+use crate::vault::autolock::{AutoLock, AutoLockSettings};
+use crate::vault::backup::{BackupDetails, BackupInfo, BackupManager, BackupPolicy};
+use crate::vault::entry::{CustomField, EntryPublic, PasswordHistoryInfo, UpdateCustomField, UpdateEntry};
+use crate::vault::export::{self, ExportData, ExportFormat};
+use crate::vault::folder::{self, EntryFilter, Folder};
+use crate::vault::generator::{Generator, GeneratorPreset};
+use crate::vault::import::{self, ImportSummary, ImportWarning, ParsedImport};
+use crate::vault::item::{Item, ItemPublic, UpdateItem};
+use crate::vault::migration::{self, Migration};
+use crate::vault::note::{SecureNote, SecureNotePublic, UpdateSecureNote};
+use crate::vault::search::{self, SearchResult};
+use crate::vault::storage;
+use crate::vault::strength;
+use crate::vault::throttle::{ThrottleStatus, UnlockThrottle};
+use crate::vault::totp::{TotpCode, TotpConfig};
+use crate::vault::trash::{Trash, TrashSettings};
+use crate::vault::url_match;
+use crate::vault::crypto::KdfParams;
 use crate::vault::{Entry, VaultCryptoManager};
 use argon2::password_hash::rand_core::RngCore;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
-use chacha20poly1305::aead::{Aead, OsRng};
+use chacha20poly1305::aead::{Aead, OsRng, Payload};
 use chacha20poly1305::{AeadCore, Key, KeyInit, XChaCha20Poly1305, XNonce};
 use core::fmt;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use time::OffsetDateTime;
 use uuid::Uuid;
 use zeroize::Zeroize;
 
-const VERSION: &str = "1.0.1";
+const VERSION: &str = "1.5.0";
+
+// First format that authenticates the header as associated data
+const AAD_SINCE: &str = "1.2.0";
 
 fn b64_to_bytes(x: &str) -> Result<Vec<u8>, String> {
     STANDARD.decode(x).map_err(|e| e.to_string())
@@ -21,6 +43,50 @@ fn bytes_to_b64<T: AsRef<[u8]>>(x: T) -> String {
     STANDARD.encode(x.as_ref())
 }
 
+/// Trims additional entry URLs and drops empty ones.
+fn clean_urls(urls: &[String]) -> Vec<String> {
+    urls.iter().map(|u| u.trim().to_string()).filter(|u| !u.is_empty()).collect()
+}
+
+/// Builds the new custom field list of an entry from the list sent by the client.
+/// Fields keep their stored value unless a new one is given.
+fn merge_custom_fields(current: &[CustomField], updates: &[UpdateCustomField]) -> VaultResult<Vec<CustomField>> {
+    updates
+        .iter()
+        .map(|update| {
+            let existing = match update.id {
+                Some(id) => Some(current.iter().find(|f| f.id == id).ok_or_else(|| VaultError {
+                    kind: VaultErrorKind::NotFound,
+                    severity: VaultErrorSeverity::Soft,
+                    message: format!("Custom field '{}' not found", id),
+                    code: "E_ENTRY_UPDATE_FIELD",
+                })?),
+                None => None,
+            };
+
+            let value = match (&update.value, existing) {
+                (Some(value), _) => value.clone(),
+                (None, Some(field)) => field.value.clone(),
+                (None, None) => {
+                    return Err(VaultError {
+                        kind: VaultErrorKind::Validation,
+                        severity: VaultErrorSeverity::Blocking,
+                        message: format!("Custom field '{}' needs a value", update.name),
+                        code: "E_ENTRY_UPDATE_FIELD",
+                    })
+                }
+            };
+
+            Ok(CustomField {
+                id: existing.map_or_else(Uuid::new_v4, |f| f.id),
+                name: update.name.clone(),
+                kind: update.kind.clone(),
+                value,
+            })
+        })
+        .collect()
+}
+
 /* -------------------------------------------------------------------------- */
 /*                                 Vault types                                */
 /* -------------------------------------------------------------------------- */
@@ -37,6 +103,9 @@ pub enum VaultErrorKind {
     NotFound,
     Internal,
     Validation,
+    PartialWrite,
+    Tampered,
+    Lockout,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -84,9 +153,29 @@ pub enum VaultChangeEvent {
     Update { source: String, id: Uuid, new: EntryPublic },
     EntryUse { source: String, id: Uuid, result: EntryUseResult }, 
     Delete { source: String, id: Uuid },
+    Restore { source: String, entry: EntryPublic },
+    Purge { source: String, id: Uuid },
+    Folders { source: String, folders: Vec<Folder> },
+    NoteCreate { source: String, note: SecureNotePublic },
+    NoteUpdate { source: String, id: Uuid, new: SecureNotePublic },
+    NoteDelete { source: String, id: Uuid },
+    ItemCreate { source: String, item: ItemPublic },
+    ItemUpdate { source: String, id: Uuid, new: ItemPublic },
+    ItemDelete { source: String, id: Uuid },
+    Import { source: String, entries: Vec<EntryPublic> },
     Status,
 }
 
+/// Decrypted contents of the vault.
+#[derive(Serialize, Deserialize, Default)]
+pub struct VaultPayload {
+    pub items: Vec<Item>,
+    #[serde(default)]
+    pub notes: Vec<SecureNote>,
+    #[serde(default)]
+    pub folders: Vec<Folder>,
+}
+
 /* ----------------------------- State & status ----------------------------- */
 #[derive(Serialize, Clone, PartialEq)]
 pub enum VaultState {
@@ -110,6 +199,11 @@ pub struct VaultCrypto {
     pub dek_nonce: String,
     pub vault_nonce: String,
     pub vault_ciphertext: String,
+    pub kdf: KdfParams,
+    pub vault_id: Uuid,
+    pub key_check: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub dek_rotated_at: Option<OffsetDateTime>,
 }
 
 #[derive(Clone)]
@@ -133,10 +227,21 @@ pub struct Vault {
     state: VaultState,
     last_error: Option<VaultError>,
 
-    entries: Vec<Entry>,
+    items: Vec<Item>,
+    notes: Vec<SecureNote>,
+    folders: Vec<Folder>,
 
     crypto: VaultCrypto,
     runtime: Option<RuntimeKeys>,
+
+    // Payload upgrades still owed after loading an older save file
+    pending_migrations: Vec<&'static Migration>,
+
+    backups: BackupManager,
+    auto_lock: AutoLock,
+    throttle: UnlockThrottle,
+    trash: Trash,
+    generator: Generator,
 }
 
 impl Drop for Vault {
@@ -149,26 +254,57 @@ impl Vault {
     pub fn new() -> Self {
         Vault {
             version: String::from(VERSION),
-            entries: Vec::new(),
+            items: Vec::new(),
+            notes: Vec::new(),
+            folders: Vec::new(),
             path: PathBuf::new(),
             state: VaultState::Uninitialized,
             crypto: VaultCrypto::default(),
             runtime: None,
             ready: false,
             last_error: None,
+            pending_migrations: Vec::new(),
+            backups: BackupManager::default(),
+            auto_lock: AutoLock::default(),
+            throttle: UnlockThrottle::default(),
+            trash: Trash::default(),
+            generator: Generator::default(),
         }
     }
 
     pub fn new_with_path(path: PathBuf) -> Self {
+        let mut backups = BackupManager::default();
+        backups.set_vault_path(&path);
+
+        let mut auto_lock = AutoLock::default();
+        auto_lock.set_vault_path(&path);
+
+        let mut throttle = UnlockThrottle::default();
+        throttle.set_vault_path(&path);
+
+        let mut trash = Trash::default();
+        trash.set_vault_path(&path);
+
+        let mut generator = Generator::default();
+        generator.set_vault_path(&path);
+
         Vault {
             version: String::from(VERSION),
-            entries: Vec::new(),
+            items: Vec::new(),
+            notes: Vec::new(),
+            folders: Vec::new(),
             path: path,
             state: VaultState::Uninitialized,
             crypto: VaultCrypto::default(),
             runtime: None,
             ready: false,
             last_error: None,
+            pending_migrations: Vec::new(),
+            backups,
+            auto_lock,
+            throttle,
+            trash,
+            generator,
         }
     }
 
@@ -176,6 +312,11 @@ impl Vault {
     /*                                     IO                                     */
     /* -------------------------------------------------------------------------- */
     pub fn set_path(&mut self, path: PathBuf) {
+        self.backups.set_vault_path(&path);
+        self.auto_lock.set_vault_path(&path);
+        self.throttle.set_vault_path(&path);
+        self.trash.set_vault_path(&path);
+        self.generator.set_vault_path(&path);
         self.path = path.clone();
     }
 
@@ -201,7 +342,55 @@ impl Vault {
         }
     }
 
+    /// Deals with a temp file left behind by a save that never finished.
+    /// If the real vault exists the temp file is stale and gets discarded,
+    /// otherwise it is promoted only if it holds a complete save file.
+    fn recover_interrupted_write(&mut self) -> VaultResult<()> {
+        let tmp = storage::temp_path(&self.path);
+
+        if !tmp.exists() {
+            return Ok(());
+        }
+
+        if self.path.exists() {
+            println!("Discarding leftover temp file from an interrupted save.");
+
+            return std::fs::remove_file(&tmp).map_err(|e| VaultError {
+                kind: VaultErrorKind::IO,
+                severity: VaultErrorSeverity::Fatal,
+                code: "E_LOAD_TMP",
+                message: format!("Couldn't remove leftover temp file: {}", e),
+            });
+        }
+
+        let is_complete = std::fs::read_to_string(&tmp)
+            .ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+            .is_some_and(|v| v["version"].is_string() && v["crypto"].is_object());
+
+        if !is_complete {
+            return Err(VaultError {
+                kind: VaultErrorKind::PartialWrite,
+                severity: VaultErrorSeverity::Fatal,
+                code: "E_LOAD_TMP",
+                message: format!(
+                    "The last save of the vault was interrupted and left an incomplete file at {}",
+                    tmp.to_string_lossy()
+                ),
+            });
+        }
+
+        std::fs::rename(&tmp, &self.path).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Fatal,
+            code: "E_LOAD_TMP",
+            message: format!("Couldn't restore vault from temp file: {}", e),
+        })
+    }
+
     pub fn try_load(&mut self) -> VaultResult<()> {
+        self.recover_interrupted_write()?;
+
         if self.is_initialized() {
             let file_content =
                 std::fs::read_to_string(self.path.clone()).map_err(|e| VaultError {
@@ -211,34 +400,71 @@ impl Vault {
                     message: format!("Couldn't read vault file: {}", e),
                 })?;
 
-            let save =
-                serde_json::from_str::<SaveFileLayout>(&file_content).map_err(|e| VaultError {
-                    kind: VaultErrorKind::Parse,
-                    severity: VaultErrorSeverity::Fatal,
-                    code: "E_LOAD",
-                    message: format!("Couldn't parse vault: {}", e),
-                })?;
+            let (save, file_version, steps) = Self::parse_save_file(&file_content, &self.version)?;
 
-            if save.version != self.version {
-                return Err(VaultError {
-                    kind: VaultErrorKind::Version,
-                    severity: VaultErrorSeverity::Fatal,
-                    code: "E_LOAD",
-                    message: format!(
-                        "Vault version ({}) doesn't match the application version. ({})",
-                        save.version, self.version
-                    ),
-                });
+            if !steps.is_empty() {
+                migration::write_backup(&self.path, &file_version)?;
             }
 
             // Load from vault file
             self.crypto = save.crypto;
+            self.pending_migrations = steps;
             self.state = VaultState::Locked;
         }
 
         Ok(())
     }
 
+    /// Parses a raw save file, upgrading its layout to `target_version` if needed.
+    /// Also returns the version it was stored with and the migration steps that
+    /// still have to be applied to its payload.
+    fn parse_save_file(
+        content: &str,
+        target_version: &str,
+    ) -> VaultResult<(SaveFileLayout, String, Vec<&'static Migration>)> {
+        let mut layout = serde_json::from_str::<serde_json::Value>(content).map_err(|e| {
+            // A file that simply stops is the signature of a torn write
+            if e.is_eof() {
+                VaultError {
+                    kind: VaultErrorKind::PartialWrite,
+                    severity: VaultErrorSeverity::Fatal,
+                    code: "E_LOAD",
+                    message: format!("Vault file is truncated: {}", e),
+                }
+            } else {
+                VaultError {
+                    kind: VaultErrorKind::Parse,
+                    severity: VaultErrorSeverity::Fatal,
+                    code: "E_LOAD",
+                    message: format!("Couldn't parse vault: {}", e),
+                }
+            }
+        })?;
+
+        let file_version = layout["version"]
+            .as_str()
+            .ok_or_else(|| VaultError {
+                kind: VaultErrorKind::Parse,
+                severity: VaultErrorSeverity::Fatal,
+                code: "E_LOAD",
+                message: "Vault file is missing its version".into(),
+            })?
+            .to_string();
+
+        // Upgrade older formats, refuse newer ones
+        let steps = migration::plan(&file_version, target_version)?;
+        migration::migrate_layout(&steps, &mut layout)?;
+
+        let save = serde_json::from_value::<SaveFileLayout>(layout).map_err(|e| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Fatal,
+            code: "E_LOAD",
+            message: format!("Couldn't parse vault: {}", e),
+        })?;
+
+        Ok((save, file_version, steps))
+    }
+
     fn convert_to_savefile(&self) -> SaveFileLayout {
         SaveFileLayout {
             version: self.version.clone(),
@@ -257,6 +483,12 @@ impl Vault {
         }
 
         self.encrypt_vault()?;
+
+        // Keep the previous version around before it gets overwritten
+        if let Err(e) = self.backups.snapshot(&self.path) {
+            println!("Couldn't back up vault before saving: {}", e);
+        }
+
         let content =
             serde_json::to_string_pretty(&self.convert_to_savefile()).map_err(|e| VaultError {
                 kind: VaultErrorKind::Parse,
@@ -265,7 +497,7 @@ impl Vault {
                 code: "E_SAVE_SERIALIZE",
             })?;
 
-        std::fs::write(self.path.clone(), content).map_err(|e| VaultError {
+        storage::write_atomic(&self.path, content.as_bytes()).map_err(|e| VaultError {
             kind: VaultErrorKind::IO,
             severity: VaultErrorSeverity::Blocking,
             message: format!("Filesystem write failed: {e}"),
@@ -277,50 +509,164 @@ impl Vault {
     /*                                AUTH / CRYPTO                               */
     /* -------------------------------------------------------------------------- */
     pub fn set_master_pw(&mut self, master_password: &str) -> VaultResult<()> {
-        let mut password_bytes = master_password.as_bytes().to_vec();
-
         // Generating DEK
         let mut dek = [0u8; 32];
         OsRng.fill_bytes(&mut dek);
 
-        // Salt for KEK
+        // Store DEK
+        self.runtime = Some(RuntimeKeys { dek });
+        dek.zeroize();
+
+        // Wrap DEK with a KEK derived from the master password
+        self.crypto.vault_id = Uuid::new_v4();
+        let params = self.crypto.kdf.clone();
+        self.rewrap_dek(master_password, params)?;
+
+        // Set vault to unlocked
+        self.state = VaultState::Unlocked;
+
+        Ok(())
+    }
+
+    /// Associated data binding a ciphertext to the header it was written with.
+    /// Formats older than `AAD_SINCE` were encrypted without any.
+    fn header_aad(crypto: &VaultCrypto, version: &str) -> VaultResult<Vec<u8>> {
+        if migration::compare_versions(version, AAD_SINCE)?.is_lt() {
+            return Ok(Vec::new());
+        }
+
+        Ok(VaultCryptoManager::header_aad(
+            version,
+            &crypto.vault_id.to_string(),
+            &crypto.kdf,
+        ))
+    }
+
+    /// Wraps the DEK that is already in memory under a fresh salt and a KEK
+    /// derived from `password` with `params`. Entries don't need re-encryption.
+    fn rewrap_dek(&mut self, password: &str, params: KdfParams) -> VaultResult<()> {
+        let dek = match &self.runtime {
+            Some(runtime) => runtime.dek,
+            None => {
+                return Err(VaultError {
+                    kind: VaultErrorKind::Access,
+                    severity: VaultErrorSeverity::Blocking,
+                    message: "Vault not initialized.".into(),
+                    code: "E_REWRAP_NEX_RUNTIME",
+                })
+            }
+        };
+
+        let mut password_bytes = password.as_bytes().to_vec();
+
         let mut salt = [0u8; 32];
         OsRng.fill_bytes(&mut salt);
 
-        // Generating KEK
         let mut kek =
-            VaultCryptoManager::derive_kek(&password_bytes, &salt).map_err(|e| VaultError {
+            VaultCryptoManager::derive_kek(&password_bytes, &salt, &params).map_err(|e| VaultError {
                 kind: VaultErrorKind::Crypto,
                 severity: VaultErrorSeverity::Blocking,
                 message: format!("Failed to derive KEK: {e}"),
-                code: "E_SET_PW_KEK",
+                code: "E_REWRAP_KEK",
             })?;
         password_bytes.zeroize();
 
-        // Wrap DEK with KEK
-        let (wrapped_dek, dek_nonce) =
-            VaultCryptoManager::wrap_dek(&dek, &kek).map_err(|e| VaultError {
-                kind: VaultErrorKind::Crypto,
-                severity: VaultErrorSeverity::Blocking,
-                message: format!("Failed to wrap DEK: {e}"),
-                code: "E_SET_PW_DEK",
-            })?;
+        let aad = VaultCryptoManager::header_aad(&self.version, &self.crypto.vault_id.to_string(), &params);
+        let key_check = VaultCryptoManager::key_check(&kek);
+        let wrapped = VaultCryptoManager::wrap_dek(&dek, &kek, &aad);
         kek.zeroize();
 
-        // Store DEK
-        self.runtime = Some(RuntimeKeys { dek });
+        let (wrapped_dek, dek_nonce) = wrapped.map_err(|e| VaultError {
+            kind: VaultErrorKind::Crypto,
+            severity: VaultErrorSeverity::Blocking,
+            message: format!("Failed to wrap DEK: {e}"),
+            code: "E_REWRAP_DEK",
+        })?;
 
-        // Convert to base64 and store it in memory
         self.crypto.wrapped_dek = bytes_to_b64(wrapped_dek);
         self.crypto.salt = bytes_to_b64(salt);
         self.crypto.dek_nonce = bytes_to_b64(dek_nonce);
-
-        // Set vault to unlocked
-        self.state = VaultState::Unlocked;
+        self.crypto.key_check = Some(bytes_to_b64(key_check));
+        self.crypto.kdf = params;
 
         Ok(())
     }
 
+    /// Rotates the master password by re-wrapping the existing DEK under a KEK
+    /// derived from the new password. The entries stay encrypted as they are.
+    /// If the save fails the old header is put back, so a later save can't
+    /// switch to the rejected password.
+    pub fn change_master_password(&mut self, old_password: &str, new_password: &str) -> VaultResult<()> {
+        self.throttled(|v| Self::unwrap_dek_with_password(&v.crypto, old_password, &v.version))?
+            .zeroize();
+
+        let previous_crypto = self.crypto.clone();
+        let params = self.crypto.kdf.clone();
+
+        let result = self.rewrap_dek(new_password, params).and_then(|_| self.save());
+        if result.is_err() {
+            self.crypto = previous_crypto;
+        }
+        result
+    }
+
+    /// Replaces the DEK with a fresh one and re-encrypts every entry under it.
+    /// On failure the previous key and header are put back, so memory never
+    /// drifts from what is on disk.
+    pub fn rotate_dek(&mut self, password: &str) -> VaultResult<()> {
+        self.throttled(|v| Self::unwrap_dek_with_password(&v.crypto, password, &v.version))?
+            .zeroize();
+
+        let mut dek = [0u8; 32];
+        OsRng.fill_bytes(&mut dek);
+
+        let previous_runtime = self.runtime.replace(RuntimeKeys { dek });
+        let previous_crypto = self.crypto.clone();
+        dek.zeroize();
+
+        let params = self.crypto.kdf.clone();
+        let result = self.rewrap_dek(password, params).and_then(|_| {
+            self.crypto.dek_rotated_at = Some(OffsetDateTime::now_utc());
+            self.save()
+        });
+
+        match result {
+            Ok(()) => {
+                if let Some(mut runtime) = previous_runtime {
+                    runtime.dek.zeroize();
+                }
+                Ok(())
+            }
+            Err(e) => {
+                if let Some(mut runtime) = std::mem::replace(&mut self.runtime, previous_runtime) {
+                    runtime.dek.zeroize();
+                }
+                self.crypto = previous_crypto;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn get_kdf_params(&self) -> KdfParams {
+        self.crypto.kdf.clone()
+    }
+
+    /// Switches the vault to new key derivation parameters. The master password
+    /// is needed again because the KEK itself is never kept in memory. If the
+    /// save fails the previous parameters and header are put back.
+    pub fn set_kdf_params(&mut self, password: &str, params: KdfParams) -> VaultResult<()> {
+        self.throttled(|v| Self::unwrap_dek_with_password(&v.crypto, password, &v.version))?
+            .zeroize();
+
+        let previous_crypto = self.crypto.clone();
+
+        let result = self.rewrap_dek(password, params).and_then(|_| self.save());
+        if result.is_err() {
+            self.crypto = previous_crypto;
+        }
+        result
+    }
+
     pub fn unlock(&mut self, password: &str) -> VaultResult<()> {
         if !matches!(self.state, VaultState::Locked) {
             return Err(VaultError { 
@@ -331,10 +677,95 @@ impl Vault {
             });
         }
 
+        let (runtime, payload) =
+            self.throttled(|v| Self::decrypt_save(&v.crypto, password, &v.pending_migrations))?;
+
+        // Store DEK in runtime variables
+        self.runtime = Some(runtime);
+        self.items = payload.items;
+        self.notes = payload.notes;
+        self.folders = payload.folders;
+
+        self.state = VaultState::Unlocked;
+        self.purge_expired_trash();
+
+        // Persist the upgraded vault so migrations only run once. The DEK is
+        // re-wrapped since the header it was bound to has changed.
+        if !self.pending_migrations.is_empty() {
+            let previous_crypto = self.crypto.clone();
+            let params = self.crypto.kdf.clone();
+
+            // A failed upgrade leaves the vault locked, as it still is on disk
+            if let Err(e) = self.rewrap_dek(password, params).and_then(|_| self.save()) {
+                self.lock();
+                self.crypto = previous_crypto;
+                return Err(e);
+            }
+
+            self.pending_migrations.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Runs a password check behind the brute-force throttle. Wrong passwords
+    /// count towards the backoff, a correct one clears it.
+    fn throttled<T, F>(&mut self, check: F) -> VaultResult<T>
+    where
+        F: FnOnce(&Self) -> VaultResult<T>,
+    {
+        self.throttle.check()?;
+
+        let result = check(self);
+
+        let recorded = match &result {
+            Ok(_) => self.throttle.reset(),
+            Err(e) if matches!(e.kind, VaultErrorKind::Auth) => self.throttle.record_failure(),
+            Err(_) => Ok(()),
+        };
+
+        if let Err(e) = recorded {
+            println!("Couldn't update failed attempt counter: {}", e);
+        }
+
+        result
+    }
+
+    pub fn get_throttle_status(&self) -> ThrottleStatus {
+        self.throttle.get_status()
+    }
+
+    /// Derives the KEK from `password` with the parameters stored in `crypto`
+    /// and unwraps the DEK with it, checking the header written under `version`.
+    /// Reads a nonce from the header. One of the wrong length can only come
+    /// from an edited file.
+    fn header_nonce(value: &str) -> VaultResult<XNonce> {
+        let bytes = b64_to_bytes(value).map_err(|e| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Blocking,
+            message: format!("Failed to decode from base64: {e}"),
+            code: "E_UNLOCK",
+        })?;
+
+        if bytes.len() != 24 {
+            return Err(VaultError {
+                kind: VaultErrorKind::Tampered,
+                severity: VaultErrorSeverity::Blocking,
+                message: "Vault header has been modified, refusing to unlock.".into(),
+                code: "E_UNLOCK_TAMPERED",
+            });
+        }
+
+        Ok(*XNonce::from_slice(&bytes))
+    }
+
+    /// Fails with an Auth error on a wrong password and Tampered on a modified header.
+    fn unwrap_dek_with_password(crypto: &VaultCrypto, password: &str, version: &str) -> VaultResult<[u8; 32]> {
+        let dek_nonce = Self::header_nonce(&crypto.dek_nonce)?;
         let mut password_bytes = password.as_bytes().to_vec();
 
         // Derive KEK
-        let salt = b64_to_bytes(&self.crypto.salt).map_err(|e| VaultError {
+        let salt = b64_to_bytes(&crypto.salt).map_err(|e| VaultError {
             kind: VaultErrorKind::Parse,
             severity: VaultErrorSeverity::Blocking,
             message: format!("Failed to decode from base64: {e}"),
@@ -342,7 +773,7 @@ impl Vault {
         })?;
 
         let mut kek =
-            VaultCryptoManager::derive_kek(&password_bytes, &salt).map_err(|e| VaultError {
+            VaultCryptoManager::derive_kek(&password_bytes, &salt, &crypto.kdf).map_err(|e| VaultError {
                 kind: VaultErrorKind::Crypto,
                 severity: VaultErrorSeverity::Blocking,
                 message: format!("Failed to derive KEK: {e}"),
@@ -352,68 +783,80 @@ impl Vault {
         password_bytes.zeroize();
 
         // Unwrap DEK
-        let dek_nonce_bytes = b64_to_bytes(&self.crypto.dek_nonce).map_err(|e| VaultError {
+        let wrapped_dek_bytes = b64_to_bytes(&crypto.wrapped_dek).map_err(|e| VaultError {
             kind: VaultErrorKind::Parse,
             severity: VaultErrorSeverity::Blocking,
             message: format!("Failed to decode from base64: {e}"),
             code: "E_UNLOCK",
         })?;
 
-        let wrapped_dek_bytes = b64_to_bytes(&self.crypto.wrapped_dek).map_err(|e| VaultError {
-            kind: VaultErrorKind::Parse,
-            severity: VaultErrorSeverity::Blocking,
-            message: format!("Failed to decode from base64: {e}"),
-            code: "E_UNLOCK",
-        })?;
+        let aad = Self::header_aad(crypto, version)?;
 
-        let dek_nonce = XNonce::from_slice(&dek_nonce_bytes);
+        let key_matches = crypto
+            .key_check
+            .as_ref()
+            .map(|check| *check == bytes_to_b64(VaultCryptoManager::key_check(&kek)));
 
-        let dek =
-            VaultCryptoManager::unwrap_dek(&wrapped_dek_bytes, &kek, dek_nonce).map_err(|_| {
-                VaultError {
-                    kind: VaultErrorKind::Auth,
-                    severity: VaultErrorSeverity::Soft,
-                    message: format!("Incorrect password."),
-                    code: "E_UNLOCK",
-                }
-            })?;
+        let dek = VaultCryptoManager::unwrap_dek(&wrapped_dek_bytes, &kek, &dek_nonce, &aad);
 
         kek.zeroize();
 
-        // Store DEK in runtime variables
-        self.runtime = Some(RuntimeKeys { dek });
+        dek.map_err(|_| match key_matches {
+            // Right key but the wrap doesn't authenticate, so the header was changed
+            Some(true) => VaultError {
+                kind: VaultErrorKind::Tampered,
+                severity: VaultErrorSeverity::Blocking,
+                message: "Vault header has been modified, refusing to unlock.".into(),
+                code: "E_UNLOCK_TAMPERED",
+            },
+            _ => VaultError {
+                kind: VaultErrorKind::Auth,
+                severity: VaultErrorSeverity::Soft,
+                message: "Incorrect password.".into(),
+                code: "E_UNLOCK",
+            },
+        })
+    }
+
+    /// Unwraps the DEK of a save file with `password` and decrypts its entries,
+    /// applying any outstanding payload migrations along the way.
+    fn decrypt_save(
+        crypto: &VaultCrypto,
+        password: &str,
+        migrations: &[&Migration],
+    ) -> VaultResult<(RuntimeKeys, VaultPayload)> {
+        // The header is authenticated as it was written, before any migration
+        let version = migrations.first().map_or(VERSION, |m| m.from);
+        let aad = Self::header_aad(crypto, version)?;
+
+        let vault_nonce = Self::header_nonce(&crypto.vault_nonce)?;
+        let dek = Self::unwrap_dek_with_password(crypto, password, version)?;
 
         // Decrypt vault data
         let cipher = XChaCha20Poly1305::new(Key::from_slice(&dek));
 
-        let vault_nonce_bytes = b64_to_bytes(&self.crypto.vault_nonce).map_err(|e| VaultError {
-            kind: VaultErrorKind::Parse,
-            severity: VaultErrorSeverity::Blocking,
-            message: format!("Failed to decode from base64: {e}"),
-            code: "E_UNLOCK",
-        })?;
-
         let vault_ciphertext_bytes =
-            b64_to_bytes(&self.crypto.vault_ciphertext).map_err(|e| VaultError {
+            b64_to_bytes(&crypto.vault_ciphertext).map_err(|e| VaultError {
                 kind: VaultErrorKind::Parse,
                 severity: VaultErrorSeverity::Blocking,
                 message: format!("Failed to decode from base64: {e}"),
                 code: "E_UNLOCK",
             })?;
 
-        let vault_nonce = XNonce::from_slice(&vault_nonce_bytes);
+        let payload = Payload {
+            msg: vault_ciphertext_bytes.as_ref(),
+            aad: &aad,
+        };
 
-        let mut vault_plaintext_bytes = cipher
-            .decrypt(vault_nonce, vault_ciphertext_bytes.as_ref())
-            .map_err(|_| VaultError {
-                kind: VaultErrorKind::Crypto,
-                severity: VaultErrorSeverity::Blocking,
-                message: format!("Failed to decrypt vault."),
-                code: "E_UNLOCK",
-            })?;
+        let mut vault_plaintext_bytes = cipher.decrypt(&vault_nonce, payload).map_err(|_| VaultError {
+            kind: VaultErrorKind::Tampered,
+            severity: VaultErrorSeverity::Blocking,
+            message: "Vault data has been modified or corrupted, refusing to unlock.".into(),
+            code: "E_UNLOCK_TAMPERED",
+        })?;
 
-        self.entries =
-            serde_json::from_slice::<Vec<Entry>>(&vault_plaintext_bytes).map_err(|_| {
+        let mut payload =
+            serde_json::from_slice::<serde_json::Value>(&vault_plaintext_bytes).map_err(|_| {
                 VaultError {
                     kind: VaultErrorKind::Parse,
                     severity: VaultErrorSeverity::Blocking,
@@ -424,34 +867,83 @@ impl Vault {
 
         vault_plaintext_bytes.zeroize();
 
-        self.state = VaultState::Unlocked;
+        migration::migrate_payload(migrations, &mut payload)?;
 
-        Ok(())
+        let payload = serde_json::from_value::<VaultPayload>(payload).map_err(|_| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Blocking,
+            message: "Failed to parse vault.".into(),
+            code: "E_UNLOCK",
+        })?;
+
+        Ok((RuntimeKeys { dek }, payload))
     }
 
     pub fn lock(&mut self) {
         if let Some(mut runtime) = self.runtime.take() {
             runtime.dek.zeroize();
         }
-        self.entries.clear();
+        self.items.clear();
+        self.notes.clear();
+        self.folders.clear();
         self.state = VaultState::Locked;
     }
 
+    /// Persists pending changes, then locks. The vault is locked even if the
+    /// save fails, since keeping the DEK around is worse than losing the
+    /// last unsaved change; the save error is still returned.
+    pub fn save_and_lock(&mut self) -> VaultResult<()> {
+        let saved = self.save();
+        self.lock();
+        saved
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                  AUTO-LOCK                                 */
+    /* -------------------------------------------------------------------------- */
+    pub fn touch(&mut self) {
+        self.auto_lock.touch();
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.auto_lock.is_idle()
+    }
+
+    pub fn get_auto_lock_settings(&self) -> AutoLockSettings {
+        self.auto_lock.get_settings()
+    }
+
+    pub fn set_auto_lock_settings(&mut self, settings: AutoLockSettings) -> VaultResult<()> {
+        self.auto_lock.set_settings(settings)
+    }
+
     pub fn encrypt_vault(&mut self) -> VaultResult<()> {
         if let Some(runtime) = &self.runtime {
             let vault_nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
             let cipher = XChaCha20Poly1305::new(Key::from_slice(&runtime.dek));
 
+            let payload = serde_json::json!({
+                "items": &self.items,
+                "notes": &self.notes,
+                "folders": &self.folders,
+            });
+
             let vault_plaintext =
-                serde_json::to_vec_pretty::<Vec<Entry>>(&self.entries).map_err(|_| VaultError {
+                serde_json::to_vec_pretty(&payload).map_err(|_| VaultError {
                     kind: VaultErrorKind::Parse,
                     severity: VaultErrorSeverity::Blocking,
                     message: format!("Failed to parse vault."),
                     code: "E_ENCRYPT_PARSE",
                 })?;
 
+            let aad = Self::header_aad(&self.crypto, &self.version)?;
+            let payload = Payload {
+                msg: vault_plaintext.as_slice(),
+                aad: &aad,
+            };
+
             let ciphertext = cipher
-                .encrypt(&vault_nonce, vault_plaintext.as_slice())
+                .encrypt(&vault_nonce, payload)
                 .map_err(|_| VaultError {
                     kind: VaultErrorKind::Crypto,
                     severity: VaultErrorSeverity::Blocking,
@@ -478,78 +970,485 @@ impl Vault {
     }
 
     /* -------------------------------------------------------------------------- */
-    /*                                   CREATE                                   */
+    /*                                   BACKUPS                                  */
     /* -------------------------------------------------------------------------- */
-    pub fn new_entry(&mut self, entry: &Entry) {
-        self.entries.push(entry.clone());
+    pub fn list_backups(&self) -> VaultResult<Vec<BackupInfo>> {
+        self.backups.list()
     }
 
-    /* -------------------------------------------------------------------------- */
-    /*                                    READ                                    */
-    /* -------------------------------------------------------------------------- */
-    pub fn get_status(&mut self) -> VaultStatus {
-        VaultStatus {
-            state: self.state.clone(),
-            ready: self.ready.clone(),
-            last_error: self.last_error.take(),
-        }
+    pub fn get_backup_policy(&self) -> BackupPolicy {
+        self.backups.get_policy()
     }
 
-    pub fn get_entries(&self) -> Vec<EntryPublic> {
-        self.entries.iter().map(EntryPublic::from).collect()
+    pub fn set_backup_policy(&mut self, policy: BackupPolicy) -> VaultResult<()> {
+        self.backups.set_policy(policy)
     }
 
-    pub fn get_entry_name(&mut self, id: &Uuid) -> VaultResult<String> {
-        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == *id) {
-            Ok(entry.name.clone())
-        } else {
-            Err(VaultError {
-                kind: VaultErrorKind::NotFound,
-                severity: VaultErrorSeverity::Soft,
-                message: format!("Entry '{}' not found", id),
-                code: "E_GET_NAME",
-            })
-        }
-    }
+    /// Opens a backup with the master password that was valid when it was taken.
+    fn open_backup(&self, id: &str, password: &str) -> VaultResult<(String, VaultPayload)> {
+        let content = self.backups.read(id)?;
+        let (save, _, steps) = Self::parse_save_file(&content, &self.version)?;
+        let (mut runtime, payload) = Self::decrypt_save(&save.crypto, password, &steps)?;
+        runtime.dek.zeroize();
 
-    pub fn get_entry_password(&mut self, id: &Uuid) -> VaultResult<String> {
-        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == *id) {
-            Ok(entry.password.clone())
-        } else {
-            Err(VaultError {
-                kind: VaultErrorKind::NotFound,
-                severity: VaultErrorSeverity::Soft,
-                message: format!("Entry '{}' not found", id),
-                code: "E_GET_PASS",
-            })
-        }
+        Ok((content, payload))
     }
 
-    /* -------------------------------------------------------------------------- */
-    /*                                   UPDATE                                   */
-    /* -------------------------------------------------------------------------- */
-    pub fn update_entry(&mut self, id: &Uuid, updated: &UpdateEntry) -> VaultResult<EntryPublic> {
-        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == *id) {
-            if let Some(label) = &updated.label {
-                entry.label = label.clone();
-            }
+    pub fn inspect_backup(&mut self, id: &str, password: &str) -> VaultResult<BackupDetails> {
+        let info = self.backups.get(id)?;
+        let (_, payload) = self.throttled(|v| v.open_backup(id, password))?;
 
-            if let Some(name) = &updated.name {
-                entry.name = name.clone();
+        Ok(BackupDetails {
+            info,
+            entry_count: payload.items.len(),
+        })
+    }
+
+    /// Replaces the vault file with a backup and unlocks it with the password
+    /// the backup was taken under. The current master password has to be given
+    /// too, and the current file is backed up first even when backups are
+    /// disabled, so a restore can itself be undone.
+    pub fn restore_backup(&mut self, id: &str, master_password: &str, password: &str) -> VaultResult<()> {
+        self.throttled(|v| Self::unwrap_dek_with_password(&v.crypto, master_password, &v.version))?
+            .zeroize();
+
+        let (content, _) = self.throttled(|v| v.open_backup(id, password))?;
+
+        self.backups.force_snapshot(&self.path)?;
+
+        storage::write_atomic(&self.path, content.as_bytes()).map_err(|e| VaultError {
+            kind: VaultErrorKind::IO,
+            severity: VaultErrorSeverity::Blocking,
+            message: format!("Filesystem write failed: {e}"),
+            code: "E_BACKUP_RESTORE",
+        })?;
+
+        self.lock();
+        self.try_load()?;
+        self.unlock(password)
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                   CREATE                                   */
+    /* -------------------------------------------------------------------------- */
+    /// Adds a copy of the entry with its strength scored here and its tags and
+    /// folder cleaned up, the returned entry is what actually got stored.
+    pub fn new_entry(&mut self, entry: &Entry) -> EntryPublic {
+        let mut entry = entry.clone();
+        entry.password_strength = strength::estimate(&entry.password);
+        entry.tags = folder::normalize_tags(&entry.tags);
+        entry.urls = clean_urls(&entry.urls);
+
+        // Entries pointing at a folder that doesn't exist land in the root
+        if self.check_folder(entry.folder_id, "E_ENTRY_CREATE").is_err() {
+            entry.folder_id = None;
+        }
+
+        let public = EntryPublic::from(&entry);
+        self.items.push(Item::Login(entry));
+
+        public
+    }
+
+    /// Adds imported logins, skipping the ones the vault already holds.
+    /// Folders are matched by name and created when missing.
+    pub fn import_entries(&mut self, parsed: ParsedImport) -> (Vec<EntryPublic>, ImportSummary) {
+        let mut summary = ImportSummary {
+            imported: 0,
+            duplicates: 0,
+            warnings: parsed.warnings,
+        };
+
+        let mut known: HashSet<(String, String, String)> =
+            self.logins().map(|e| import::login_key(&e.url, &e.name, &e.password)).collect();
+        let mut created = Vec::new();
+
+        for entry in parsed.entries {
+            if !known.insert(entry.dedupe_key()) {
+                summary.duplicates += 1;
+                summary.warnings.push(ImportWarning {
+                    row: entry.row,
+                    message: format!("'{}' is already in the vault and was skipped", entry.label),
+                });
+                continue;
             }
 
-            if let Some(pass) = &updated.password {
-                entry.password = pass.clone();
+            let folder_id = self.folder_for_path(&entry.folder);
+            created.push(self.new_entry(&entry.into_entry(folder_id)));
+        }
+
+        summary.imported = created.len();
+
+        (created, summary)
+    }
+
+    /// Id of the folder at the end of `path`, creating the missing ones.
+    fn folder_for_path(&mut self, path: &[String]) -> Option<Uuid> {
+        let mut parent = None;
+
+        for name in path.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+            let existing = self
+                .folders
+                .iter()
+                .find(|f| f.parent_id == parent && f.name.eq_ignore_ascii_case(name))
+                .map(|f| f.id);
+
+            parent = match existing {
+                Some(id) => Some(id),
+                None => self.create_folder(name, parent).ok().map(|f| f.id),
+            };
+        }
+
+        parent
+    }
+
+    pub fn new_item(&mut self, item: &Item) -> ItemPublic {
+        match item {
+            Item::Login(entry) => ItemPublic::Login(self.new_entry(entry)),
+            _ => {
+                self.items.push(item.clone());
+                ItemPublic::from(item)
             }
+        }
+    }
+
+    pub fn new_note(&mut self, note: &SecureNote) {
+        self.notes.push(note.clone());
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                    READ                                    */
+    /* -------------------------------------------------------------------------- */
+    pub fn get_status(&mut self) -> VaultStatus {
+        VaultStatus {
+            state: self.state.clone(),
+            ready: self.ready.clone(),
+            last_error: self.last_error.take(),
+        }
+    }
 
-            if let Some(strength) = &updated.password_strength {
-                entry.password_strength = strength.clone();
+    /// Logins outside of the trash. Trashed ones are only reachable
+    /// through the trash methods.
+    fn logins(&self) -> impl Iterator<Item = &Entry> {
+        self.items.iter().filter_map(Item::as_login).filter(|e| e.deleted_at.is_none())
+    }
+
+    fn logins_mut(&mut self) -> impl Iterator<Item = &mut Entry> {
+        self.items
+            .iter_mut()
+            .filter_map(Item::as_login_mut)
+            .filter(|e| e.deleted_at.is_none())
+    }
+
+    pub fn get_entries(&self) -> Vec<EntryPublic> {
+        self.logins().map(EntryPublic::from).collect()
+    }
+
+    pub fn get_entries_filtered(&self, filter: &EntryFilter) -> Vec<EntryPublic> {
+        let folders = filter.folder_id.map(|id| {
+            if filter.include_subfolders {
+                folder::subtree(&self.folders, id)
+            } else {
+                vec![id]
+            }
+        });
+
+        self.logins()
+            .filter(|e| match &folders {
+                Some(ids) => e.folder_id.is_some_and(|f| ids.contains(&f)),
+                None => !filter.unfiled || e.folder_id.is_none(),
+            })
+            .filter(|e| match &filter.tag {
+                Some(tag) => e.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+                None => true,
+            })
+            .map(EntryPublic::from)
+            .collect()
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        search::search(self.logins(), query, limit)
+    }
+
+    /// Entries with a URL matching `url`, most specific matches first and
+    /// the most used ones first among those.
+    pub fn find_entries_for_url(&self, url: &str) -> VaultResult<Vec<EntryPublic>> {
+        let target = url_match::parse(url).filter(|u| u.has_host()).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::Validation,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("'{}' is not a valid URL", url),
+            code: "E_URL_MATCH",
+        })?;
+
+        let mut matches: Vec<(u8, &Entry)> = self
+            .logins()
+            .filter_map(|e| url_match::match_entry(e, &target).map(|specificity| (specificity, e)))
+            .collect();
+
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.uses.cmp(&a.1.uses)));
+
+        Ok(matches.into_iter().map(|(_, e)| EntryPublic::from(e)).collect())
+    }
+
+    /// Every tag in use, sorted case-insensitively.
+    pub fn get_tags(&self) -> Vec<String> {
+        let all: Vec<String> = self.logins().flat_map(|e| e.tags.iter().cloned()).collect();
+        let mut tags = folder::normalize_tags(&all);
+        tags.sort_by_key(|t| t.to_lowercase());
+        tags
+    }
+
+    pub fn get_items(&self) -> Vec<ItemPublic> {
+        self.items.iter().filter(|i| !i.is_trashed()).map(ItemPublic::from).collect()
+    }
+
+    pub fn get_item(&self, id: &Uuid) -> VaultResult<Item> {
+        self.items.iter().find(|i| i.id() == *id && !i.is_trashed()).cloned().ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Item '{}' not found", id),
+            code: "E_GET_ITEM",
+        })
+    }
+
+    pub fn get_item_secret(&self, id: &Uuid, field: &str) -> VaultResult<String> {
+        self.get_item(id)?.secret(field).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Item '{}' has no secret field '{}'", id, field),
+            code: "E_GET_ITEM_SECRET",
+        })
+    }
+
+    pub fn get_entry_name(&mut self, id: &Uuid) -> VaultResult<String> {
+        if let Some(entry) = self.logins_mut().find(|e| e.id == *id) {
+            Ok(entry.name.clone())
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found", id),
+                code: "E_GET_NAME",
+            })
+        }
+    }
+
+    pub fn get_entry_password(&mut self, id: &Uuid) -> VaultResult<String> {
+        if let Some(entry) = self.logins_mut().find(|e| e.id == *id) {
+            Ok(entry.password.clone())
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found", id),
+                code: "E_GET_PASS",
+            })
+        }
+    }
+
+    pub fn get_entry_field(&mut self, id: &Uuid, field_id: &Uuid) -> VaultResult<String> {
+        let entry = self.logins().find(|e| e.id == *id).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Entry '{}' not found", id),
+            code: "E_GET_FIELD",
+        })?;
+
+        entry
+            .fields
+            .iter()
+            .find(|f| f.id == *field_id)
+            .map(|f| f.value.clone())
+            .ok_or_else(|| VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Custom field '{}' not found", field_id),
+                code: "E_GET_FIELD",
+            })
+    }
+
+    pub fn get_password_history(&self, id: &Uuid) -> VaultResult<Vec<PasswordHistoryInfo>> {
+        if let Some(entry) = self.logins().find(|e| e.id == *id) {
+            Ok(entry.password_history.iter().map(PasswordHistoryInfo::from).collect())
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found", id),
+                code: "E_GET_HISTORY",
+            })
+        }
+    }
+
+    pub fn get_history_password(&self, id: &Uuid, history_id: &Uuid) -> VaultResult<String> {
+        let entry = self.logins().find(|e| e.id == *id).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Entry '{}' not found", id),
+            code: "E_GET_HISTORY",
+        })?;
+
+        entry
+            .password_history
+            .iter()
+            .find(|h| h.id == *history_id)
+            .map(|h| h.password.clone())
+            .ok_or_else(|| VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Password history item '{}' not found", history_id),
+                code: "E_GET_HISTORY",
+            })
+    }
+
+    /// Ids of logins other than `exclude` that use or used `password`.
+    pub fn find_password_reuse(&self, password: &str, exclude: Option<&Uuid>) -> Vec<Uuid> {
+        self.logins()
+            .filter(|e| Some(&e.id) != exclude)
+            .filter(|e| e.known_passwords().any(|p| p == password))
+            .map(|e| e.id)
+            .collect()
+    }
+
+    /// SHA-1 of every current login password, for the breach audit which
+    /// runs on these without holding on to the vault.
+    pub fn get_password_hashes(&self) -> Vec<(Uuid, String)> {
+        self.logins().map(|e| (e.id, audit::sha1_hex(&e.password))).collect()
+    }
+
+    pub fn security_report(&self, max_age_days: u32) -> SecurityReport {
+        audit::security_report(&self.logins().collect::<Vec<_>>(), max_age_days)
+    }
+
+    /// Writes the logins outside of the trash to `path` and returns how many
+    /// there were. The master password is asked again so an unlocked vault
+    /// left unattended can't simply be dumped.
+    pub fn export(
+        &mut self,
+        format: ExportFormat,
+        path: &Path,
+        master_password: &str,
+        export_password: Option<&str>,
+        key_file: Option<&Path>,
+    ) -> VaultResult<usize> {
+        self.throttled(|v| Self::unwrap_dek_with_password(&v.crypto, master_password, &v.version))?
+            .zeroize();
+
+        let data = ExportData {
+            entries: self.logins().collect(),
+            folders: &self.folders,
+        };
+
+        let mut content = export::render(format, &data, export_password, key_file, &self.crypto.kdf)?;
+        let written = storage::write_atomic(path, &content);
+        content.zeroize();
+
+        written.map_err(|e| export::export_error(VaultErrorKind::IO, format!("Couldn't write export: {}", e)))?;
+
+        Ok(data.entries.len())
+    }
+
+    pub fn get_totp_code(&self, id: &Uuid) -> VaultResult<TotpCode> {
+        let entry = self.logins().find(|e| e.id == *id).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Entry '{}' not found", id),
+            code: "E_GET_TOTP",
+        })?;
+
+        let secret = entry.totp.as_ref().ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Entry '{}' has no TOTP secret", id),
+            code: "E_GET_TOTP",
+        })?;
+
+        let config = TotpConfig::parse(secret).map_err(|e| VaultError {
+            kind: VaultErrorKind::Parse,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Stored TOTP secret is invalid: {}", e),
+            code: "E_GET_TOTP",
+        })?;
+
+        Ok(config.now())
+    }
+
+    pub fn get_entry_notes(&self, id: &Uuid) -> VaultResult<String> {
+        if let Some(entry) = self.logins().find(|e| e.id == *id) {
+            Ok(entry.notes.clone())
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found", id),
+                code: "E_GET_NOTES",
+            })
+        }
+    }
+
+    pub fn get_notes(&self) -> Vec<SecureNotePublic> {
+        self.notes.iter().map(SecureNotePublic::from).collect()
+    }
+
+    pub fn get_note_content(&self, id: &Uuid) -> VaultResult<String> {
+        if let Some(note) = self.notes.iter().find(|n| n.id == *id) {
+            Ok(note.content.clone())
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Note '{}' not found", id),
+                code: "E_GET_NOTE",
+            })
+        }
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                   UPDATE                                   */
+    /* -------------------------------------------------------------------------- */
+    pub fn update_entry(&mut self, id: &Uuid, updated: &UpdateEntry) -> VaultResult<EntryPublic> {
+        if let Some(entry) = self.logins_mut().find(|e| e.id == *id) {
+            // Resolved first, so a bad field list leaves the entry untouched
+            if let Some(fields) = &updated.fields {
+                entry.fields = merge_custom_fields(&entry.fields, fields)?;
+            }
+
+            if let Some(label) = &updated.label {
+                entry.label = label.clone();
+            }
+
+            if let Some(name) = &updated.name {
+                entry.name = name.clone();
+            }
+
+            if let Some(pass) = &updated.password {
+                entry.set_password(pass.clone(), strength::estimate(pass));
             }
 
             if let Some(url) = &updated.url {
                 entry.url = url.clone();
             }
 
+            if let Some(urls) = &updated.urls {
+                entry.urls = clean_urls(urls);
+            }
+
+            if let Some(mode) = updated.url_match {
+                entry.url_match = mode;
+            }
+
+            if let Some(notes) = &updated.notes {
+                entry.notes = notes.clone();
+            }
+
+            if let Some(tags) = &updated.tags {
+                entry.tags = folder::normalize_tags(tags);
+            }
+
+            if let Some(totp) = &updated.totp {
+                entry.totp = Some(totp.trim().to_string()).filter(|t| !t.is_empty());
+            }
+
             entry.modified_at = OffsetDateTime::now_utc();
 
             Ok(EntryPublic::from(&*entry))
@@ -563,8 +1462,146 @@ impl Vault {
         }
     }
 
+    pub fn update_note(&mut self, id: &Uuid, updated: &UpdateSecureNote) -> VaultResult<SecureNotePublic> {
+        if let Some(note) = self.notes.iter_mut().find(|n| n.id == *id) {
+            if let Some(label) = &updated.label {
+                note.label = label.clone();
+            }
+
+            if let Some(content) = &updated.content {
+                note.content = content.clone();
+            }
+
+            if let Some(favorite) = updated.favorite {
+                note.favorite = favorite;
+            }
+
+            note.modified_at = OffsetDateTime::now_utc();
+
+            Ok(SecureNotePublic::from(&*note))
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Note '{}' not found", id),
+                code: "E_NOTE_UPDATE",
+            })
+        }
+    }
+
+    /// Applies a partial update to a non-login item. `check` sees the updated
+    /// item before it is stored, so invalid results leave the vault untouched.
+    pub fn update_item<F>(&mut self, id: &Uuid, update: &UpdateItem, check: F) -> VaultResult<ItemPublic>
+    where
+        F: FnOnce(&Item) -> VaultResult<()>,
+    {
+        let existing = self.items.iter_mut().find(|i| i.id() == *id && !i.is_trashed()).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Item '{}' not found", id),
+            code: "E_ITEM_UPDATE",
+        })?;
+
+        let mut item = existing.clone();
+
+        item.apply(update).map_err(|e| VaultError {
+            kind: VaultErrorKind::Validation,
+            severity: VaultErrorSeverity::Blocking,
+            message: e,
+            code: "E_ITEM_UPDATE",
+        })?;
+
+        check(&item)?;
+        *existing = item;
+
+        Ok(ItemPublic::from(&*existing))
+    }
+
+    /// Adds and removes tags on several entries at once.
+    pub fn assign_tags(&mut self, ids: &[Uuid], add: &[String], remove: &[String]) -> VaultResult<Vec<EntryPublic>> {
+        if let Some(missing) = ids.iter().find(|id| !self.logins().any(|e| e.id == **id)) {
+            return Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found", missing),
+                code: "E_ENTRY_TAGS",
+            });
+        }
+
+        let now = OffsetDateTime::now_utc();
+
+        Ok(self
+            .logins_mut()
+            .filter(|e| ids.contains(&e.id))
+            .map(|entry| {
+                entry.tags.retain(|t| !remove.iter().any(|r| r.trim().eq_ignore_ascii_case(t)));
+                entry.tags.extend(add.iter().cloned());
+                entry.tags = folder::normalize_tags(&entry.tags);
+                entry.modified_at = now;
+
+                EntryPublic::from(&*entry)
+            })
+            .collect())
+    }
+
+    pub fn move_entries(&mut self, ids: &[Uuid], folder_id: Option<Uuid>) -> VaultResult<Vec<EntryPublic>> {
+        self.check_folder(folder_id, "E_ENTRY_MOVE")?;
+
+        if let Some(missing) = ids.iter().find(|id| !self.logins().any(|e| e.id == **id)) {
+            return Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found", missing),
+                code: "E_ENTRY_MOVE",
+            });
+        }
+
+        Ok(self
+            .logins_mut()
+            .filter(|e| ids.contains(&e.id))
+            .map(|entry| {
+                entry.folder_id = folder_id;
+                EntryPublic::from(&*entry)
+            })
+            .collect())
+    }
+
+    pub fn restore_password(&mut self, id: &Uuid, history_id: &Uuid) -> VaultResult<EntryPublic> {
+        let entry = self.logins_mut().find(|e| e.id == *id).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Entry '{}' not found", id),
+            code: "E_ENTRY_RESTORE_PASS",
+        })?;
+
+        entry.restore_password(history_id).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Password history item '{}' not found", history_id),
+            code: "E_ENTRY_RESTORE_PASS",
+        })?;
+
+        entry.modified_at = OffsetDateTime::now_utc();
+
+        Ok(EntryPublic::from(&*entry))
+    }
+
+    pub fn purge_password_history(&mut self, id: &Uuid) -> VaultResult<EntryPublic> {
+        if let Some(entry) = self.logins_mut().find(|e| e.id == *id) {
+            entry.password_history.clear();
+            Ok(EntryPublic::from(&*entry))
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found", id),
+                code: "E_ENTRY_PURGE_HISTORY",
+            })
+        }
+    }
+
     pub fn toggle_favorite(&mut self, id: &Uuid) -> VaultResult<EntryPublic> {
-        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == *id) {
+        if let Some(entry) = self.logins_mut().find(|e| e.id == *id) {
             entry.favorite = !entry.favorite;
             Ok(EntryPublic::from(&*entry))
         } else {
@@ -577,14 +1614,12 @@ impl Vault {
         }
     }
 
+    /// Counts a use of any item type, not only logins.
+    /// Records a use of any item but a trashed login.
     pub fn use_entry(&mut self, id: &Uuid) -> VaultResult<EntryUseResult> {
-        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == *id) {
+        if let Some(item) = self.items.iter_mut().find(|i| i.id() == *id && !i.is_trashed()) {
             let now = OffsetDateTime::now_utc();
-
-            entry.last_used = Some(now);
-            entry.uses += 1;
-
-            let uses = entry.uses;
+            let uses = item.record_use(now);
 
             Ok(EntryUseResult { last_use: now, uses: uses })
         } else {
@@ -600,9 +1635,10 @@ impl Vault {
     /* -------------------------------------------------------------------------- */
     /*                                   DELETE                                   */
     /* -------------------------------------------------------------------------- */
+    /// Moves an entry to the trash, see `purge_entry` for deleting it for good.
     pub fn delete_entry(&mut self, id: &Uuid) -> VaultResult<()> {
-        if let Some(entry_pos) = self.entries.iter().position(|e| e.id == *id) {
-            self.entries.remove(entry_pos);
+        if let Some(entry) = self.logins_mut().find(|e| e.id == *id) {
+            entry.deleted_at = Some(OffsetDateTime::now_utc());
             Ok(())
         } else {
             Err(VaultError {
@@ -613,4 +1649,496 @@ impl Vault {
             })
         }
     }
+
+    /// Deletes a non-login item for good. Logins go through the trash with `delete_entry`.
+    pub fn delete_item(&mut self, id: &Uuid) -> VaultResult<()> {
+        if let Some(item_pos) = self.items.iter().position(|i| i.id() == *id && i.as_login().is_none()) {
+            self.items.remove(item_pos);
+            Ok(())
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Item '{}' not found", id),
+                code: "E_ITEM_DELETE",
+            })
+        }
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                   FOLDERS                                  */
+    /* -------------------------------------------------------------------------- */
+    pub fn get_folders(&self) -> Vec<Folder> {
+        self.folders.clone()
+    }
+
+    /// Fails unless `id` is the root (`None`) or an existing folder.
+    fn check_folder(&self, id: Option<Uuid>, code: &'static str) -> VaultResult<()> {
+        match id {
+            Some(id) if !self.folders.iter().any(|f| f.id == id) => Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Folder '{}' not found", id),
+                code,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn folder_mut(&mut self, id: &Uuid, code: &'static str) -> VaultResult<&mut Folder> {
+        self.folders.iter_mut().find(|f| f.id == *id).ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Folder '{}' not found", id),
+            code,
+        })
+    }
+
+    pub fn create_folder(&mut self, name: &str, parent_id: Option<Uuid>) -> VaultResult<Folder> {
+        self.check_folder(parent_id, "E_FOLDER_CREATE")?;
+
+        let folder = Folder {
+            id: Uuid::new_v4(),
+            name: name.trim().to_string(),
+            parent_id,
+        };
+
+        self.folders.push(folder.clone());
+
+        Ok(folder)
+    }
+
+    pub fn rename_folder(&mut self, id: &Uuid, name: &str) -> VaultResult<Folder> {
+        let folder = self.folder_mut(id, "E_FOLDER_RENAME")?;
+        folder.name = name.trim().to_string();
+
+        Ok(folder.clone())
+    }
+
+    pub fn move_folder(&mut self, id: &Uuid, parent_id: Option<Uuid>) -> VaultResult<Folder> {
+        self.check_folder(parent_id, "E_FOLDER_MOVE")?;
+
+        // A folder can't end up inside itself
+        if parent_id.is_some_and(|p| folder::subtree(&self.folders, *id).contains(&p)) {
+            return Err(VaultError {
+                kind: VaultErrorKind::Validation,
+                severity: VaultErrorSeverity::Soft,
+                message: "A folder can't be moved into itself or one of its subfolders".into(),
+                code: "E_FOLDER_MOVE",
+            });
+        }
+
+        let folder = self.folder_mut(id, "E_FOLDER_MOVE")?;
+        folder.parent_id = parent_id;
+
+        Ok(folder.clone())
+    }
+
+    /// Removes a folder. Its subfolders and entries move up to its parent.
+    pub fn delete_folder(&mut self, id: &Uuid) -> VaultResult<Vec<EntryPublic>> {
+        let parent_id = self.folder_mut(id, "E_FOLDER_DELETE")?.parent_id;
+
+        self.folders.retain(|f| f.id != *id);
+
+        for folder in self.folders.iter_mut().filter(|f| f.parent_id == Some(*id)) {
+            folder.parent_id = parent_id;
+        }
+
+        // Trashed entries move too, so restoring them doesn't point at a missing folder
+        Ok(self
+            .items
+            .iter_mut()
+            .filter_map(Item::as_login_mut)
+            .filter(|e| e.folder_id == Some(*id))
+            .filter_map(|entry| {
+                entry.folder_id = parent_id;
+                entry.deleted_at.is_none().then(|| EntryPublic::from(&*entry))
+            })
+            .collect())
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                    TRASH                                   */
+    /* -------------------------------------------------------------------------- */
+    pub fn list_trash(&self) -> Vec<EntryPublic> {
+        self.items
+            .iter()
+            .filter_map(Item::as_login)
+            .filter(|e| e.deleted_at.is_some())
+            .map(EntryPublic::from)
+            .collect()
+    }
+
+    fn trashed_position(&self, id: &Uuid) -> Option<usize> {
+        self.items
+            .iter()
+            .position(|i| i.is_trashed() && i.id() == *id)
+    }
+
+    pub fn restore_entry(&mut self, id: &Uuid) -> VaultResult<EntryPublic> {
+        let entry = self
+            .trashed_position(id)
+            .and_then(|pos| self.items[pos].as_login_mut())
+            .ok_or_else(|| VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found in the trash", id),
+                code: "E_ENTRY_RESTORE",
+            })?;
+
+        entry.deleted_at = None;
+
+        Ok(EntryPublic::from(&*entry))
+    }
+
+    pub fn purge_entry(&mut self, id: &Uuid) -> VaultResult<()> {
+        if let Some(pos) = self.trashed_position(id) {
+            self.items.remove(pos);
+            Ok(())
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Entry '{}' not found in the trash", id),
+                code: "E_ENTRY_PURGE",
+            })
+        }
+    }
+
+    /// Drops trashed entries older than the retention period, returning their ids.
+    pub fn purge_expired_trash(&mut self) -> Vec<Uuid> {
+        let expired: Vec<Uuid> = self
+            .items
+            .iter()
+            .filter_map(Item::as_login)
+            .filter(|e| e.deleted_at.is_some_and(|d| self.trash.is_expired(d)))
+            .map(|e| e.id)
+            .collect();
+
+        self.items.retain(|i| !expired.contains(&i.id()));
+
+        expired
+    }
+
+    pub fn get_trash_settings(&self) -> TrashSettings {
+        self.trash.get_settings()
+    }
+
+    pub fn set_trash_settings(&mut self, settings: TrashSettings) -> VaultResult<()> {
+        self.trash.set_settings(settings)
+    }
+
+    pub fn get_generator_presets(&self) -> Vec<GeneratorPreset> {
+        self.generator.get_presets()
+    }
+
+    pub fn get_generator_preset(&self, id: &Uuid) -> VaultResult<GeneratorPreset> {
+        self.generator.get_preset(id).cloned().ok_or_else(|| VaultError {
+            kind: VaultErrorKind::NotFound,
+            severity: VaultErrorSeverity::Soft,
+            message: format!("Preset '{}' not found", id),
+            code: "E_GENERATOR_PRESETS",
+        })
+    }
+
+    pub fn save_generator_preset(&mut self, preset: GeneratorPreset) -> VaultResult<()> {
+        self.generator.save_preset(preset)
+    }
+
+    pub fn delete_generator_preset(&mut self, id: &Uuid) -> VaultResult<()> {
+        self.generator.delete_preset(id)
+    }
+
+    pub fn delete_note(&mut self, id: &Uuid) -> VaultResult<()> {
+        if let Some(note_pos) = self.notes.iter().position(|n| n.id == *id) {
+            self.notes.remove(note_pos);
+            Ok(())
+        } else {
+            Err(VaultError {
+                kind: VaultErrorKind::NotFound,
+                severity: VaultErrorSeverity::Soft,
+                message: format!("Note '{}' not found", id),
+                code: "E_NOTE_DELETE",
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    /// A login as the frontend sends it.
+    fn login(label: &str, password: &str) -> Entry {
+        serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "createdAt": "2024-01-01T00:00:00Z",
+            "modifiedAt": "2024-01-01T00:00:00Z",
+            "uses": 0,
+            "label": label,
+            "url": format!("https://{}.example.com", label.to_lowercase()),
+            "name": "me@example.com",
+            "password": password,
+            "passwordStrength": "Weak",
+            "favorite": false,
+        }))
+        .unwrap()
+    }
+
+    /// Creates and saves an unlocked vault with two logins and a note. Key
+    /// derivation is kept cheap, the tests are about the files.
+    fn create_vault(path: &Path) -> Vault {
+        let mut vault = Vault::new_with_path(path.to_path_buf());
+        vault.crypto.kdf = KdfParams { m_cost: 1024, t_cost: 1, p_cost: 1, ..KdfParams::default() };
+        vault.set_master_pw(PASSWORD).unwrap();
+
+        vault.new_entry(&login("Mail", "mail-password"));
+        vault.new_entry(&login("Bank", "bank-password"));
+        vault.new_note(&SecureNote {
+            id: Uuid::new_v4(),
+            created_at: OffsetDateTime::now_utc(),
+            modified_at: OffsetDateTime::now_utc(),
+            label: "Recovery codes".into(),
+            content: "1234-5678".into(),
+            favorite: false,
+        });
+
+        vault.save().unwrap();
+        vault
+    }
+
+    /// Loads the vault file like the app does on start.
+    fn reopen(path: &Path) -> Vault {
+        let mut vault = Vault::new_with_path(path.to_path_buf());
+        vault.try_load().unwrap();
+        vault
+    }
+
+    #[test]
+    fn recovery_discards_temp_file_next_to_intact_vault() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+        let tmp = storage::temp_path(&path);
+
+        create_vault(&path);
+        let original = fs::read(&path).unwrap();
+
+        // Even a temp file that looks complete loses against an existing vault
+        fs::write(&tmp, b"{\"version\": \"1.5.0\", \"crypto\": {}}").unwrap();
+
+        let mut vault = Vault::new_with_path(path.clone());
+        vault.recover_interrupted_write().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), original);
+        assert!(!tmp.exists());
+    }
+
+    #[test]
+    fn recovery_refuses_truncated_temp_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+        let tmp = storage::temp_path(&path);
+
+        create_vault(&path);
+        let content = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // First save of a new vault, cut off halfway
+        fs::write(&tmp, &content[..content.len() / 2]).unwrap();
+
+        let mut vault = Vault::new_with_path(path.clone());
+        let error = vault.recover_interrupted_write().unwrap_err();
+
+        assert!(matches!(error.kind, VaultErrorKind::PartialWrite));
+        assert!(!path.exists());
+        assert_eq!(fs::read(&tmp).unwrap(), &content[..content.len() / 2]);
+    }
+
+    #[test]
+    fn recovery_promotes_complete_temp_file_when_rename_was_lost() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+        let tmp = storage::temp_path(&path);
+
+        create_vault(&path);
+        let content = fs::read(&path).unwrap();
+
+        // A crash between the rename and the directory fsync can lose the
+        // rename, leaving the finished save under its temp name only
+        fs::rename(&path, &tmp).unwrap();
+
+        let mut vault = Vault::new_with_path(path.clone());
+        vault.recover_interrupted_write().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), content);
+        assert!(!tmp.exists());
+
+        // Once the rename sticks there is nothing left to recover
+        vault.recover_interrupted_write().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), content);
+
+        let mut vault = reopen(&path);
+        vault.unlock(PASSWORD).unwrap();
+        assert_eq!(vault.get_entries().len(), 2);
+    }
+
+    /// Every login password and note, to compare a vault across key changes.
+    fn secrets(vault: &mut Vault) -> Vec<String> {
+        let ids: Vec<Uuid> = vault.get_entries().iter().map(|e| e.id).collect();
+        let mut secrets: Vec<String> = ids.iter().map(|id| vault.get_entry_password(id).unwrap()).collect();
+
+        for note in vault.get_notes() {
+            secrets.push(vault.get_note_content(&note.id).unwrap());
+        }
+        secrets
+    }
+
+    #[test]
+    fn old_password_stops_working_after_change() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+
+        let mut vault = create_vault(&path);
+        let before = secrets(&mut vault);
+        vault.change_master_password(PASSWORD, "new password").unwrap();
+        drop(vault);
+
+        let mut vault = reopen(&path);
+        let error = vault.unlock(PASSWORD).unwrap_err();
+        assert!(matches!(error.kind, VaultErrorKind::Auth));
+        assert!(!vault.is_unlocked());
+
+        vault.unlock("new password").unwrap();
+        assert_eq!(secrets(&mut vault), before);
+    }
+
+    #[test]
+    fn rotated_key_no_longer_decrypts_vault() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+
+        let mut vault = create_vault(&path);
+        let before = secrets(&mut vault);
+        let old_dek = vault.runtime.as_ref().unwrap().dek;
+        vault.rotate_dek(PASSWORD).unwrap();
+        assert_ne!(vault.runtime.as_ref().unwrap().dek, old_dek);
+        drop(vault);
+
+        let mut vault = reopen(&path);
+        let nonce = b64_to_bytes(&vault.crypto.vault_nonce).unwrap();
+        let ciphertext = b64_to_bytes(&vault.crypto.vault_ciphertext).unwrap();
+        let aad = Vault::header_aad(&vault.crypto, VERSION).unwrap();
+        let decrypted = XChaCha20Poly1305::new(Key::from_slice(&old_dek))
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &aad });
+        assert!(decrypted.is_err());
+
+        vault.unlock(PASSWORD).unwrap();
+        assert_eq!(secrets(&mut vault), before);
+    }
+
+    #[test]
+    fn old_password_stops_working_after_change_and_rotation() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+
+        let mut vault = create_vault(&path);
+        let before = secrets(&mut vault);
+        vault.change_master_password(PASSWORD, "new password").unwrap();
+
+        let error = vault.rotate_dek(PASSWORD).unwrap_err();
+        assert!(matches!(error.kind, VaultErrorKind::Auth));
+        vault.rotate_dek("new password").unwrap();
+        drop(vault);
+
+        let mut vault = reopen(&path);
+        let error = vault.unlock(PASSWORD).unwrap_err();
+        assert!(matches!(error.kind, VaultErrorKind::Auth));
+
+        vault.unlock("new password").unwrap();
+        assert_eq!(secrets(&mut vault), before);
+    }
+
+    #[test]
+    fn failed_save_keeps_old_password() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+
+        let mut vault = create_vault(&path);
+
+        // A directory in the way of the temp file fails the save, even for root
+        let tmp = storage::temp_path(&path);
+        fs::create_dir(&tmp).unwrap();
+        assert!(vault.change_master_password(PASSWORD, "new password").is_err());
+        fs::remove_dir(&tmp).unwrap();
+
+        // The next save must not pick up the rejected password
+        vault.save().unwrap();
+        drop(vault);
+
+        let mut vault = reopen(&path);
+        let error = vault.unlock("new password").unwrap_err();
+        assert!(matches!(error.kind, VaultErrorKind::Auth));
+        vault.unlock(PASSWORD).unwrap();
+    }
+
+    #[test]
+    fn failed_save_keeps_old_kdf_params() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+
+        let mut vault = create_vault(&path);
+        let before = vault.get_kdf_params();
+        let stronger = KdfParams { m_cost: 2048, ..before.clone() };
+
+        let tmp = storage::temp_path(&path);
+        fs::create_dir(&tmp).unwrap();
+        assert!(vault.set_kdf_params(PASSWORD, stronger).is_err());
+        fs::remove_dir(&tmp).unwrap();
+
+        assert_eq!(vault.get_kdf_params(), before);
+        vault.save().unwrap();
+        drop(vault);
+
+        let mut vault = reopen(&path);
+        assert_eq!(vault.get_kdf_params(), before);
+        vault.unlock(PASSWORD).unwrap();
+    }
+
+    #[test]
+    fn truncated_header_nonce_is_tampering() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+        create_vault(&path);
+        let original = fs::read_to_string(&path).unwrap();
+
+        for field in ["dek_nonce", "vault_nonce"] {
+            let mut file: serde_json::Value = serde_json::from_str(&original).unwrap();
+            file["crypto"][field] = bytes_to_b64([0u8; 12]).into();
+            fs::write(&path, file.to_string()).unwrap();
+
+            let mut vault = reopen(&path);
+            let error = vault.unlock(PASSWORD).unwrap_err();
+            assert!(matches!(error.kind, VaultErrorKind::Tampered), "{}", field);
+            assert!(!vault.is_unlocked());
+        }
+    }
+
+    #[test]
+    fn trashed_login_cannot_be_used() {
+        let dir = TempDir::new().unwrap();
+        let mut vault = create_vault(&dir.path().join("vault.json"));
+        let id = vault.get_entries()[0].id;
+
+        vault.use_entry(&id).unwrap();
+        vault.delete_entry(&id).unwrap();
+
+        let error = vault.use_entry(&id).err().unwrap();
+        assert!(matches!(error.kind, VaultErrorKind::NotFound));
+        assert_eq!(vault.list_trash()[0].uses, 1);
+    }
 }
@@ -0,0 +1,382 @@
+"""Writes the KDBX 4 fixtures used by the KeePass import tests.
+
+Written independently of the Rust reader, from the KDBX 4 format as KeePass
+and KeePassXC implement it, so the tests don't just check the reader against
+itself. Every database holds the same groups and entries, laid out the way
+KeePassXC saves them. Needs the `cryptography` package.
+
+    python3 generate.py
+"""
+
+import base64
+import datetime
+import gzip
+import hashlib
+import hmac
+import os
+import struct
+import uuid
+
+from cryptography.hazmat.primitives import padding
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+from cryptography.hazmat.primitives.kdf.argon2 import Argon2id
+
+PASSWORD = "fixture-password"
+
+CIPHER_AES256 = bytes.fromhex("31c1f2e6bf714350be5805216afc5aff")
+CIPHER_CHACHA20 = bytes.fromhex("d6038a2b8b6f4cb5a524339a31dbb59a")
+KDF_AES = bytes.fromhex("c9d9f39a628a4460bf740d08c18a4fea")
+KDF_ARGON2D = bytes.fromhex("ef636ddf8c29444b91f7a9a403e30a0c")
+KDF_ARGON2ID = bytes.fromhex("9e298b1956db4773b23dfc3ec6f0a1e6")
+
+# Cheap settings, the tests open every fixture
+ARGON2_MEMORY = 1024 * 1024
+ARGON2_ITERATIONS = 2
+ARGON2_PARALLELISM = 2
+AES_KDF_ROUNDS = 1000
+
+MASK = (1 << 64) - 1
+
+
+# --------------------------------------------------------------------------- #
+#                Argon2, the cryptography package lacks Argon2d               #
+# --------------------------------------------------------------------------- #
+def blake2b_long(length, data):
+    if length <= 64:
+        return hashlib.blake2b(struct.pack("<I", length) + data, digest_size=length).digest()
+
+    v = hashlib.blake2b(struct.pack("<I", length) + data).digest()
+    out = v[:32]
+    while length - len(out) > 64:
+        v = hashlib.blake2b(v).digest()
+        out += v[:32]
+    return out + hashlib.blake2b(v, digest_size=length - len(out)).digest()
+
+
+def mix(v, a, b, c, d):
+    def rotr(x, n):
+        return ((x >> n) | (x << (64 - n))) & MASK
+
+    def blamka(x, y):
+        return (x + y + 2 * (x & 0xFFFFFFFF) * (y & 0xFFFFFFFF)) & MASK
+
+    v[a] = blamka(v[a], v[b]); v[d] = rotr(v[d] ^ v[a], 32)
+    v[c] = blamka(v[c], v[d]); v[b] = rotr(v[b] ^ v[c], 24)
+    v[a] = blamka(v[a], v[b]); v[d] = rotr(v[d] ^ v[a], 16)
+    v[c] = blamka(v[c], v[d]); v[b] = rotr(v[b] ^ v[c], 63)
+
+
+def permute(v):
+    mix(v, 0, 4, 8, 12); mix(v, 1, 5, 9, 13); mix(v, 2, 6, 10, 14); mix(v, 3, 7, 11, 15)
+    mix(v, 0, 5, 10, 15); mix(v, 1, 6, 11, 12); mix(v, 2, 7, 8, 13); mix(v, 3, 4, 9, 14)
+
+
+def compress(x, y):
+    r = [a ^ b for a, b in zip(x, y)]
+    q = r[:]
+    for row in range(8):
+        v = q[16 * row:16 * row + 16]
+        permute(v)
+        q[16 * row:16 * row + 16] = v
+    for col in range(8):
+        idx = [16 * row + 2 * col + k for row in range(8) for k in (0, 1)]
+        v = [q[i] for i in idx]
+        permute(v)
+        for i, value in zip(idx, v):
+            q[i] = value
+    return [a ^ b for a, b in zip(q, r)]
+
+
+def words(data):
+    return list(struct.unpack("<128Q", data))
+
+
+def argon2(kind, password, salt, memory_kib, passes, lanes, length=32):
+    """Argon2d (kind 0) or Argon2id (kind 2), version 0x13."""
+    h0 = hashlib.blake2b(
+        struct.pack("<6I", lanes, length, memory_kib, passes, 0x13, kind)
+        + struct.pack("<I", len(password)) + password
+        + struct.pack("<I", len(salt)) + salt
+        + struct.pack("<I", 0) + struct.pack("<I", 0)
+    ).digest()
+
+    blocks = 4 * lanes * (memory_kib // (4 * lanes))
+    columns = blocks // lanes
+    segment = columns // 4
+    memory = [[None] * columns for _ in range(lanes)]
+
+    for lane in range(lanes):
+        for j in (0, 1):
+            memory[lane][j] = words(blake2b_long(1024, h0 + struct.pack("<II", j, lane)))
+
+    zero = [0] * 128
+    for pass_ in range(passes):
+        for slice_ in range(4):
+            for lane in range(lanes):
+                independent = kind == 2 and pass_ == 0 and slice_ < 2
+                addresses = None
+                counter = 0
+
+                for index in range(segment):
+                    j = slice_ * segment + index
+                    if pass_ == 0 and j < 2:
+                        continue
+
+                    if independent:
+                        if addresses is None or index % 128 == 0:
+                            counter += 1
+                            z = [pass_, lane, slice_, blocks, passes, kind, counter] + [0] * 121
+                            addresses = compress(zero, compress(zero, z))
+                        pseudo = addresses[index % 128]
+                    else:
+                        pseudo = memory[lane][(j - 1) % columns][0]
+
+                    j1, j2 = pseudo & 0xFFFFFFFF, pseudo >> 32
+                    ref_lane = lane if pass_ == 0 and slice_ == 0 else j2 % lanes
+                    same = ref_lane == lane
+
+                    if pass_ == 0:
+                        area = slice_ * segment + (index - 1 if same else (-1 if index == 0 else 0))
+                        start = 0
+                    else:
+                        area = columns - segment + (index - 1 if same else (-1 if index == 0 else 0))
+                        start = 0 if slice_ == 3 else (slice_ + 1) * segment
+
+                    x = (j1 * j1) >> 32
+                    ref = (start + area - 1 - ((area * x) >> 32)) % columns
+
+                    block = compress(memory[lane][(j - 1) % columns], memory[ref_lane][ref])
+                    if pass_ > 0:
+                        block = [a ^ b for a, b in zip(block, memory[lane][j])]
+                    memory[lane][j] = block
+
+    final = memory[0][columns - 1]
+    for lane in range(1, lanes):
+        final = [a ^ b for a, b in zip(final, memory[lane][columns - 1])]
+
+    return blake2b_long(length, struct.pack("<128Q", *final))
+
+
+# --------------------------------------------------------------------------- #
+#                                KDBX 4 writer                                #
+# --------------------------------------------------------------------------- #
+def variant_dictionary(items):
+    out = struct.pack("<H", 0x0100)
+    for name, kind, value in items:
+        name = name.encode()
+        out += bytes([kind]) + struct.pack("<i", len(name)) + name + struct.pack("<i", len(value)) + value
+    return out + b"\x00"
+
+
+def transform_key(kdf, composite):
+    salt = os.urandom(32)
+
+    if kdf == "aes":
+        items = [("$UUID", 0x42, KDF_AES), ("R", 0x05, struct.pack("<Q", AES_KDF_ROUNDS)), ("S", 0x42, salt)]
+        encryptor = Cipher(algorithms.AES(salt), modes.ECB()).encryptor()
+        key = composite
+        for _ in range(AES_KDF_ROUNDS):
+            key = encryptor.update(key)
+        return items, hashlib.sha256(key).digest()
+
+    kind = {"argon2d": 0, "argon2id": 2}[kdf]
+    items = [
+        ("$UUID", 0x42, KDF_ARGON2D if kind == 0 else KDF_ARGON2ID),
+        ("S", 0x42, salt),
+        ("P", 0x04, struct.pack("<I", ARGON2_PARALLELISM)),
+        ("M", 0x05, struct.pack("<Q", ARGON2_MEMORY)),
+        ("I", 0x05, struct.pack("<Q", ARGON2_ITERATIONS)),
+        ("V", 0x04, struct.pack("<I", 0x13)),
+    ]
+    key = argon2(kind, composite, salt, ARGON2_MEMORY // 1024, ARGON2_ITERATIONS, ARGON2_PARALLELISM)
+    return items, key
+
+
+def block_key(hmac_key, index):
+    return hashlib.sha512(struct.pack("<Q", index) + hmac_key).digest()
+
+
+class InnerStream:
+    """ChaCha20 keystream shared by all protected values, in document order."""
+
+    def __init__(self, key):
+        digest = hashlib.sha512(key).digest()
+        self.cipher = Cipher(algorithms.ChaCha20(digest[:32], b"\x00" * 4 + digest[32:44]), mode=None).encryptor()
+
+    def protect(self, value):
+        return base64.b64encode(self.cipher.update(value.encode())).decode()
+
+
+def write_kdbx(path, composite, cipher, kdf, document):
+    def field(kind, value):
+        return bytes([kind]) + struct.pack("<I", len(value)) + value
+
+    seed = os.urandom(32)
+    iv = os.urandom(16 if cipher == "aes" else 12)
+    kdf_items, transformed = transform_key(kdf, composite)
+
+    header = struct.pack("<IIHH", 0x9AA2D903, 0xB54BFB67, 0, 4)
+    header += field(2, CIPHER_AES256 if cipher == "aes" else CIPHER_CHACHA20)
+    header += field(3, struct.pack("<I", 1))
+    header += field(4, seed)
+    header += field(7, iv)
+    header += field(11, variant_dictionary(kdf_items))
+    header += field(0, b"\r\n\r\n")
+
+    cipher_key = hashlib.sha256(seed + transformed).digest()
+    hmac_key = hashlib.sha512(seed + transformed + b"\x01").digest()
+
+    stream_key = os.urandom(64)
+    xml = document(InnerStream(stream_key)).encode()
+    inner = field(1, struct.pack("<I", 3)) + field(2, stream_key) + field(3, b"\x01attachment") + field(0, b"") + xml
+    payload = gzip.compress(inner)
+
+    if cipher == "aes":
+        padder = padding.PKCS7(128).padder()
+        encryptor = Cipher(algorithms.AES(cipher_key), modes.CBC(iv)).encryptor()
+        encrypted = encryptor.update(padder.update(payload) + padder.finalize()) + encryptor.finalize()
+    else:
+        encryptor = Cipher(algorithms.ChaCha20(cipher_key, b"\x00" * 4 + iv), mode=None).encryptor()
+        encrypted = encryptor.update(payload)
+
+    out = header + hashlib.sha256(header).digest()
+    out += hmac.new(block_key(hmac_key, (1 << 64) - 1), header, hashlib.sha256).digest()
+
+    blocks = [encrypted[i:i + 1024 * 1024] for i in range(0, len(encrypted), 1024 * 1024)] + [b""]
+    for index, block in enumerate(blocks):
+        size = struct.pack("<i", len(block))
+        mac = hmac.new(block_key(hmac_key, index), struct.pack("<Q", index) + size + block, hashlib.sha256)
+        out += mac.digest() + size + block
+
+    with open(path, "wb") as f:
+        f.write(out)
+
+
+# --------------------------------------------------------------------------- #
+#                                   Contents                                  #
+# --------------------------------------------------------------------------- #
+def time(year, month, day):
+    epoch = datetime.datetime(1, 1, 1, tzinfo=datetime.timezone.utc)
+    seconds = int((datetime.datetime(year, month, day, tzinfo=datetime.timezone.utc) - epoch).total_seconds())
+    return base64.b64encode(struct.pack("<q", seconds)).decode()
+
+
+def new_uuid():
+    return base64.b64encode(uuid.uuid4().bytes).decode()
+
+
+def escape(text):
+    return text.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;")
+
+
+def document(stream):
+    """The database as KeePassXC would save it. Protected values are encrypted
+    as they are written, so they end up in the keystream in document order."""
+    out = []
+    recycle_bin = new_uuid()
+
+    def string(key, value, protected=False):
+        if protected:
+            out.append(f'<String><Key>{key}</Key><Value Protected="True">{stream.protect(value)}</Value></String>')
+        else:
+            out.append(f"<String><Key>{key}</Key><Value>{escape(value)}</Value></String>")
+
+    def entry(title, user, password, url, modified, tags="", notes="", extra=None, history=None):
+        out.append(f"<Entry><UUID>{new_uuid()}</UUID><IconID>0</IconID><Tags>{tags}</Tags>")
+        out.append(
+            f"<Times><CreationTime>{time(2019, 1, 1)}</CreationTime><LastModificationTime>{modified}</LastModificationTime>"
+            f"<LastAccessTime>{modified}</LastAccessTime><ExpiryTime>{modified}</ExpiryTime><Expires>False</Expires>"
+            f"<UsageCount>0</UsageCount><LocationChanged>{modified}</LocationChanged></Times>"
+        )
+        string("Notes", notes)
+        string("Password", password, protected=True)
+        string("Title", title)
+        string("URL", url)
+        string("UserName", user)
+        if extra:
+            extra()
+        out.append("<AutoType><Enabled>True</Enabled><DataTransferObfuscation>0</DataTransferObfuscation></AutoType>")
+        if history:
+            out.append("<History>")
+            history()
+            out.append("</History>")
+        out.append("</Entry>")
+
+    def group(name, uuid_=None):
+        out.append(f"<Group><UUID>{uuid_ or new_uuid()}</UUID><Name>{name}</Name><IconID>48</IconID>")
+
+    def mail_fields():
+        string("KP2A_URL", "https://webmail.example.com")
+        string("PIN", "4321", protected=True)
+        string("Security question", "First pet?")
+        string("otp", "otpauth://totp/Mail:alice?secret=JBSWY3DPEHPK3PXP&issuer=Mail", protected=True)
+
+    def mail_history():
+        entry("Mail", "alice@example.com", "first-mail-password", "https://mail.example.com", time(2020, 1, 1))
+        entry("Mail", "alice@example.com", "second-mail-password", "https://mail.example.com", time(2022, 6, 1),
+              extra=lambda: string("PIN", "1234", protected=True))
+        entry("Mail (old)", "alice@example.com", "second-mail-password", "https://mail.example.com", time(2023, 3, 1))
+
+    out.append('<?xml version="1.0" encoding="UTF-8" standalone="yes"?>\n<KeePassFile>')
+    out.append(
+        "<Meta><Generator>KeePassXC</Generator><DatabaseName>Fixture</DatabaseName>"
+        "<MemoryProtection><ProtectTitle>False</ProtectTitle><ProtectUserName>False</ProtectUserName>"
+        "<ProtectPassword>True</ProtectPassword><ProtectURL>False</ProtectURL><ProtectNotes>False</ProtectNotes></MemoryProtection>"
+        f"<RecycleBinEnabled>True</RecycleBinEnabled><RecycleBinUUID>{recycle_bin}</RecycleBinUUID></Meta>"
+    )
+    out.append("<Root>")
+    group("Root")
+
+    entry("Mail", "alice@example.com", "current-mail-password", "https://mail.example.com", time(2024, 5, 1),
+          tags="mail;work", notes="Main inbox\nSecond line", extra=mail_fields, history=mail_history)
+    entry("Router", "admin", "", "http://192.168.1.1", time(2024, 1, 1))
+
+    # Ahead of the bank entry, its values still take up keystream
+    group("Recycle Bin", recycle_bin)
+    entry("Deleted login", "old", "deleted-password", "https://old.example.com", time(2024, 2, 1))
+    out.append("</Group>")
+
+    group("Banking")
+    group("EU")
+    entry("Bank", "acct-001", "bank-pässword-€", "https://bank.example.eu", time(2024, 3, 1),
+          extra=lambda: string("Card PIN", "9876", protected=True))
+    out.append("</Group></Group>")
+
+    out.append("</Group><DeletedObjects/></Root></KeePassFile>")
+    return "".join(out)
+
+
+def key_file(path):
+    key = os.urandom(32)
+    digest = hashlib.sha256(key).hexdigest()[:8].upper()
+    data = key.hex().upper()
+
+    with open(path, "w") as f:
+        f.write(
+            '<?xml version="1.0" encoding="UTF-8"?>\n<KeyFile>\n\t<Meta>\n\t\t<Version>2.0</Version>\n\t</Meta>\n'
+            f'\t<Key>\n\t\t<Data Hash="{digest}">\n\t\t\t{data[:32]}\n\t\t\t{data[32:]}\n\t\t</Data>\n\t</Key>\n</KeyFile>\n'
+        )
+
+    return key
+
+
+def composite(password=None, key=None):
+    digest = hashlib.sha256()
+    if password is not None:
+        digest.update(hashlib.sha256(password.encode()).digest())
+    if key is not None:
+        digest.update(key)
+    return digest.digest()
+
+
+if __name__ == "__main__":
+    # The Argon2 above has to agree with a known implementation first
+    check = Argon2id(salt=b"s" * 16, length=32, iterations=2, lanes=2, memory_cost=64)
+    assert argon2(2, b"password", b"s" * 16, 64, 2, 2) == check.derive(b"password")
+
+    os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+    write_kdbx("argon2d-aes.kdbx", composite(PASSWORD), "aes", "argon2d", document)
+    write_kdbx("argon2id-chacha20.kdbx", composite(PASSWORD), "chacha20", "argon2id", document)
+    write_kdbx("aes-kdf-aes.kdbx", composite(PASSWORD), "aes", "aes", document)
+    write_kdbx("key-file-only.kdbx", composite(key=key_file("key-file-only.keyx")), "chacha20", "argon2d", document)
@@ -23,7 +23,7 @@ const UpdateForm = ({
     onClose: () => void;
 }) => {
 
-    const { generatePassword, evaluatePassword } = passwordUtils();
+    const { generatePassword } = passwordUtils();
 
     const { getEntryPassword } = useVault();
 
@@ -48,8 +48,7 @@ const UpdateForm = ({
             onSubmit={(data) => {
                 onSubmit(entry.id, {
                     ...data,
-                    ...(data.url && { url: normalizeUrl(data.url) }),
-                    ...(data.password && { passwordStrength: evaluatePassword(data.password).strength })
+                    ...(data.url && { url: normalizeUrl(data.url) })
                 });
             }}
         >
@@ -1,5 +1,5 @@
 import { createContext, ReactNode, useContext, useEffect, useRef, useState } from "react";
-import { Entry, EntryDTO, EntryUseResult, Err, Ok, toEntry, toEntryDTO, UpdateEntry, VaultCallbacks, VaultChangeEvent, VaultContextType, VaultError, VaultResult, VaultStatus } from "../types/general";
+import { Entry, EntryDTO, EntryUseResult, Err, Folder, Ok, toEntry, toEntryDTO, UpdateEntry, VaultCallbacks, VaultChangeEvent, VaultContextType, VaultError, VaultResult, VaultStatus } from "../types/general";
 import { useError } from "./error";
 import { invoke } from "@tauri-apps/api/core";
 import { Event, listen, UnlistenFn } from "@tauri-apps/api/event";
@@ -12,6 +12,7 @@ export const VaultProvider = ({ children }: { children: ReactNode }) => {
     const { fatalError, addError, clearFatalError } = useError();
     
     const [entries, setEntries] = useState<Entry[] | null>(null);
+    const [folders, setFolders] = useState<Folder[] | null>(null);
     const [status, setStatus] = useState<VaultStatus | null>(null);
     const [loading, setLoading] = useState<boolean>(true);
     const { statistics } = useVaultStatistics(entries);
@@ -104,6 +105,23 @@ export const VaultProvider = ({ children }: { children: ReactNode }) => {
         }
     }
 
+    const getVaultFolders = async (
+        callbacks ?: VaultCallbacks<Folder[]>
+    ): Promise<VaultResult<Folder[]>> => {
+        try {
+            const result = await invoke("vault_get_folders") as Folder[];
+
+            callbacks?.ok?.(result);
+            return Ok(result);
+        }
+        catch (e) {
+            const error = e as VaultError;
+
+            callbacks?.err?.(error);
+            return Err(error);
+        }
+    }
+
     const getEntryPassword = async (
         id: string,
         callbacks ?: VaultCallbacks<string>
@@ -259,6 +277,12 @@ export const VaultProvider = ({ children }: { children: ReactNode }) => {
             return;
         }
 
+        // Always the whole tree, so the window that made the change can take it too
+        if (vaultEvent.type === "Folders") {
+            setFolders(vaultEvent.payload.folders);
+            return;
+        }
+
         if (getCurrentWebview().label === vaultEvent.payload.source) return;
 
         switch (vaultEvent.type) {
@@ -266,6 +290,10 @@ export const VaultProvider = ({ children }: { children: ReactNode }) => {
                 const entry = toEntry(vaultEvent.payload.entry);
                 setEntries(prev => prev ? [...prev, entry] : [entry]);
                 break;
+            case "Import":
+                const imported = vaultEvent.payload.entries.map(toEntry);
+                setEntries(prev => prev ? [...prev, ...imported] : imported);
+                break;
             case "Update":
                 const updated = toEntry(vaultEvent.payload.new);
                 setEntries(p => p?.map(e => e.id === vaultEvent.payload.id ? updated : e) || null);
@@ -282,6 +310,13 @@ export const VaultProvider = ({ children }: { children: ReactNode }) => {
             case "Delete":
                 setEntries(p => p?.filter(e => e.id != vaultEvent.payload.id) || null);
                 break;
+            case "Restore":
+                const restored = toEntry(vaultEvent.payload.entry);
+                setEntries(prev => prev ? [...prev, restored] : [restored]);
+                break;
+            case "Purge":
+                setEntries(p => p?.filter(e => e.id != vaultEvent.payload.id) || null);
+                break;
             default:
                 break;
         }
@@ -311,6 +346,10 @@ export const VaultProvider = ({ children }: { children: ReactNode }) => {
                 ok: setEntries,
                 err: addError
             });
+            getVaultFolders({
+                ok: setFolders,
+                err: addError
+            });
         }
     }, [status]);
 
@@ -321,6 +360,7 @@ export const VaultProvider = ({ children }: { children: ReactNode }) => {
         setupVault,
         unlockVault, 
         entries, 
+        folders,
         statistics,
         createEntry, 
         updateEntry, 
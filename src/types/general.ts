@@ -109,11 +109,43 @@ export type PasswordEvaluation = {
     suggestions: string[]
 }
 
+// Omitted options fall back to the backend defaults
+export type PasswordOptions = {
+    type: "Password",
+    length?: number,
+    lowercase?: boolean,
+    uppercase?: boolean,
+    digits?: boolean,
+    symbols?: boolean,
+    minLowercase?: number,
+    minUppercase?: number,
+    minDigits?: number,
+    minSymbols?: number,
+    excludeAmbiguous?: boolean,
+    exclude?: string
+}
+
+export type PassphraseOptions = {
+    type: "Passphrase",
+    words?: number,
+    separator?: string,
+    capitalize?: boolean,
+    includeNumber?: boolean
+}
+
+export type GeneratorOptions = PasswordOptions | PassphraseOptions;
+
+export type GeneratorPreset = {
+    id: string,
+    name: string,
+    options: GeneratorOptions
+}
+
 
 /* -------------------------------------------------------------------------- */
 /*                           Vault types and helpers                          */
 /* -------------------------------------------------------------------------- */
-export type VaultErrorKind = "IO" | "Parse" | "Version" | "Access" | "Auth" | "Crypto" | "NotFound" | "Internal" | "Validation";
+export type VaultErrorKind = "IO" | "Parse" | "Version" | "Access" | "Auth" | "Crypto" | "NotFound" | "Internal" | "Validation" | "PartialWrite" | "Tampered" | "Lockout";
 
 export type VaultErrorSeverity = "Soft" | "Blocking" | "Fatal";
 
@@ -134,6 +166,25 @@ export type VaultStatus = {
     last_error: VaultError | null
 }
 
+export type CustomFieldKind = "Text" | "Hidden" | "Url" | "Email" | "Number" | "Date";
+
+export type CustomField = {
+    id?: string,
+    name: string,
+    kind: CustomFieldKind,
+    // Null for hidden fields, use vault_copy_entry_field to get those
+    value: string | null
+}
+
+export type UpdateCustomField = {
+    id?: string,
+    name: string,
+    kind: CustomFieldKind,
+    value?: string
+}
+
+export type UrlMatchMode = "BaseDomain" | "Host" | "StartsWith" | "RegEx" | "Never";
+
 export type Entry = {
     id: string,
     createdAt: Date,
@@ -144,7 +195,19 @@ export type Entry = {
     url: string,
     name: string,
     favorite: boolean,
-    passwordStrength: PasswordStrength
+    passwordStrength: PasswordStrength,
+    fields?: CustomField[],
+    hasTotp?: boolean,
+    historyCount?: number,
+    deletedAt?: Date | null,
+    tags?: string[],
+    folderId?: string | null,
+    // Matched along with url
+    urls?: string[],
+    urlMatch?: UrlMatchMode,
+    // Only sent when creating, never returned by the backend
+    notes?: string,
+    totp?: string
 }
 
 export type EntryDTO = {
@@ -157,7 +220,15 @@ export type EntryDTO = {
     url: string,
     name: string,
     favorite: boolean,
-    passwordStrength: PasswordStrength
+    passwordStrength: PasswordStrength,
+    fields?: CustomField[],
+    hasTotp?: boolean,
+    historyCount?: number,
+    deletedAt?: string | null,
+    tags?: string[],
+    folderId?: string | null,
+    urls?: string[],
+    urlMatch?: UrlMatchMode
 }
 
 export type UpdateEntry = {
@@ -165,7 +236,138 @@ export type UpdateEntry = {
     url?: string,
     name?: string,
     password?: string,
-    passwordStrength?: PasswordStrength,
+    fields?: UpdateCustomField[],
+    notes?: string,
+    // An empty string removes the secret
+    totp?: string,
+    tags?: string[],
+    urls?: string[],
+    urlMatch?: UrlMatchMode,
+}
+
+export type BreachResult = {
+    id: string,
+    // 0 when the password wasn't found in the breach list
+    count: number
+}
+
+// Payload of the audit_progress event
+export type AuditProgress = {
+    checked: number,
+    total: number
+}
+
+export type ReusedPassword = {
+    // Only identifies the group within one report
+    id: string,
+    entries: string[]
+}
+
+export type SecurityReport = {
+    total: number,
+    reused: ReusedPassword[],
+    weak: string[],
+    old: string[],
+    insecureUrls: string[],
+    // Entries with the same label and username
+    duplicates: string[][]
+}
+
+export type ImportFormat = "Bitwarden" | "KeePassXml" | "Kdbx" | "OnePassword" | "Csv" | "Nebula";
+
+export type ExportFormat = "Csv" | "Bitwarden" | "KeePassXml" | "Kdbx" | "Nebula";
+
+export type ImportWarning = {
+    row: number,
+    message: string
+}
+
+export type ImportSummary = {
+    imported: number,
+    duplicates: number,
+    warnings: ImportWarning[]
+}
+
+export type PasswordHistoryInfo = {
+    id: string,
+    passwordStrength: PasswordStrength,
+    replacedAt: string
+}
+
+export type Folder = {
+    id: string,
+    name: string,
+    parentId: string | null
+}
+
+export type EntryFilter = {
+    folderId?: string,
+    includeSubfolders?: boolean,
+    // Only entries outside of any folder, ignored when folderId is set
+    unfiled?: boolean,
+    tag?: string
+}
+
+export type TrashSettings = {
+    // 0 keeps deleted entries until purged by hand
+    retentionDays: number
+}
+
+export type TotpCode = {
+    code: string,
+    secondsRemaining: number,
+    period: number
+}
+
+export type ItemMeta = {
+    id: string,
+    createdAt: string,
+    modifiedAt: string,
+    lastUsed: string | null,
+    uses: number,
+    label: string,
+    favorite: boolean
+}
+
+export type WifiSecurity = "None" | "Wep" | "WpaPersonal" | "WpaEnterprise";
+
+// Public projections, secret fields are fetched with vault_get_item_secret
+export type ItemDTO =
+  | ({ type: "Login" } & EntryDTO)
+  | ({ type: "Card" } & ItemMeta & { cardholderName: string, brand: string, lastDigits: string, expMonth: string, expYear: string })
+  | ({ type: "Identity" } & ItemMeta & {
+        firstName: string, lastName: string, email: string, phone: string, company: string,
+        address: string, city: string, state: string, postalCode: string, country: string
+    })
+  | ({ type: "Wifi" } & ItemMeta & { ssid: string, security: WifiSecurity, hidden: boolean })
+  | ({ type: "License" } & ItemMeta & { product: string, licensedTo: string, email: string, purchaseDate: string, expiresAt: string })
+  | ({ type: "SshKey" } & ItemMeta & { publicKey: string, fingerprint: string | null });
+
+export type ItemType = ItemDTO["type"];
+
+export type ItemSecretField =
+  | "password" | "number" | "cvv" | "pin" | "ssn" | "passportNumber" | "licenseKey" | "privateKey" | "passphrase";
+
+export type SecureNote = {
+    id: string,
+    createdAt: Date,
+    modifiedAt: Date,
+    label: string,
+    favorite: boolean
+}
+
+export type SecureNoteDTO = {
+    id: string,
+    createdAt: string,
+    modifiedAt: string,
+    label: string,
+    favorite: boolean
+}
+
+export type UpdateSecureNote = {
+    label?: string,
+    content?: string,
+    favorite?: boolean,
 }
 
 export type VaultStatistics = {
@@ -205,6 +407,8 @@ export type VaultContextType = {
 
     // Entries
     entries: Entry[] | null,
+    // Folder tree, kept in sync across windows
+    folders: Folder[] | null,
     statistics: VaultStatistics | null,
     createEntry: (
         entry: Entry,
@@ -246,14 +450,16 @@ export const toEntry = (e: EntryDTO): Entry => ({
     ...e,
     createdAt: new Date(e.createdAt),
     modifiedAt: new Date(e.modifiedAt),
-    lastUsed: e.lastUsed ? new Date(e.lastUsed) : null
+    lastUsed: e.lastUsed ? new Date(e.lastUsed) : null,
+    deletedAt: e.deletedAt ? new Date(e.deletedAt) : null
 });
 
 export const toEntryDTO = (e: Entry): EntryDTO => ({
     ...e,
     createdAt: e.createdAt.toISOString(),
     modifiedAt: e.modifiedAt.toISOString(),
-    lastUsed: e.lastUsed?.toISOString() || null
+    lastUsed: e.lastUsed?.toISOString() || null,
+    deletedAt: e.deletedAt?.toISOString() || null
 });
 
 export type EntryUseResult = {
@@ -266,7 +472,17 @@ export type VaultChangeEvent =
   | { type: "Create"; payload: { source: string; entry: EntryDTO } }
   | { type: "Update"; payload: { source: string; id: string, new: EntryDTO } }
   | { type: "EntryUse"; payload: { source: string; id: string, result: EntryUseResult } }
-  | { type: "Delete"; payload: { source: string; id: string } };
+  | { type: "Delete"; payload: { source: string; id: string } }
+  | { type: "Restore"; payload: { source: string; entry: EntryDTO } }
+  | { type: "Purge"; payload: { source: string; id: string } }
+  | { type: "Folders"; payload: { source: string; folders: Folder[] } }
+  | { type: "NoteCreate"; payload: { source: string; note: SecureNoteDTO } }
+  | { type: "NoteUpdate"; payload: { source: string; id: string, new: SecureNoteDTO } }
+  | { type: "NoteDelete"; payload: { source: string; id: string } }
+  | { type: "ItemCreate"; payload: { source: string; item: ItemDTO } }
+  | { type: "ItemUpdate"; payload: { source: string; id: string, new: ItemDTO } }
+  | { type: "ItemDelete"; payload: { source: string; id: string } }
+  | { type: "Import"; payload: { source: string; entries: EntryDTO[] } };
 
 /* -------------------------------------------------------------------------- */
 /*                          Result type and helpers                           */
@@ -330,6 +546,26 @@ export type TextInputProps = {
 /* -------------------------------------------------------------------------- */
 /*                                Overlay types                               */
 /* -------------------------------------------------------------------------- */
+export type SearchField = "Label" | "Name" | "Host" | "Tag";
+
+// Offsets are UTF-16 code units, end is exclusive
+export type MatchRange = {
+    start: number,
+    end: number
+}
+
+export type FieldMatch = {
+    field: SearchField,
+    value: string,
+    ranges: MatchRange[]
+}
+
+export type SearchResultDTO = {
+    entry: EntryDTO,
+    score: number,
+    matches: FieldMatch[]
+}
+
 export type SearchResults = {
     query: string,
     results: Entry[],